@@ -2,13 +2,17 @@
 // SPDX-License-Identifier: Apache-2.0
 
 //! Generates yaml CRD resources from rust code.
-//! By default this will target the helm chart's `crds` directory!
+//! By default this will target the helm chart's `files/crds` directory, the chart's templates add
+//! the conversion webhook and decide which versions are served!
 //! Designed to be used inside of a mise command that sets the `CRDS_DIR` and `SCHEMA_DIR` environment variables.
 use std::{fs::File, io::Write, path::Path};
 
-use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::CustomResourceDefinition;
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::{
+    CustomResourceDefinition, CustomResourceDefinitionVersion,
+};
 use kube::CustomResourceExt;
-use stickerbomb_crd::Labeler;
+use kube::core::crd::merge_crds;
+use stickerbomb_crd::{STORAGE_VERSION, v1_alpha1, v1_beta1};
 
 #[allow(clippy::unwrap_used)]
 fn generate_crd_files(crd: &CustomResourceDefinition, crds_dir: &Path, schema_dir: &Path) {
@@ -21,7 +25,18 @@ fn generate_crd_files(crd: &CustomResourceDefinition, crds_dir: &Path, schema_di
         .write_all(yaml.as_bytes())
         .unwrap();
 
-    let version = &crd.spec.versions[0];
+    for version in &crd.spec.versions {
+        generate_schema_file(crd, version, schema_dir);
+    }
+}
+
+#[allow(clippy::unwrap_used)]
+fn generate_schema_file(
+    crd: &CustomResourceDefinition,
+    version: &CustomResourceDefinitionVersion,
+    schema_dir: &Path,
+) {
+    let kind = crd.spec.names.kind.to_lowercase();
     let openapi_schema = version
         .schema
         .as_ref()
@@ -73,8 +88,23 @@ fn main() {
     let crds_dir = Path::new(&crds_dir_str);
     let schema_dir = Path::new(&schema_dir_str);
 
-    // Add your CRDs here
-    let crds = vec![Labeler::crd()];
+    // Add your CRDs here, every version of a kind is merged into a single CRD
+    let crds = vec![
+        merge_crds(
+            vec![v1_alpha1::Labeler::crd(), v1_beta1::Labeler::crd()],
+            STORAGE_VERSION,
+        )
+        .unwrap(),
+        merge_crds(
+            vec![
+                v1_alpha1::ClusterLabeler::crd(),
+                v1_beta1::ClusterLabeler::crd(),
+            ],
+            STORAGE_VERSION,
+        )
+        .unwrap(),
+        v1_alpha1::RegoLibrary::crd(),
+    ];
 
     for crd in crds {
         generate_crd_files(&crd, crds_dir, schema_dir);
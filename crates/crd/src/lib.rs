@@ -4,6 +4,14 @@
 //! Custom resource deffinitions for stickerbomb operator
 
 pub mod v1_alpha1;
+pub mod v1_beta1;
+pub mod validation;
 
+/// Version the API server stores the custom resources in, the other versions are converted by the
+/// operator's conversion webhook
+pub const STORAGE_VERSION: &str = "v1alpha1";
+
+pub use v1_alpha1::ClusterLabeler;
 pub use v1_alpha1::Labeler;
 pub use v1_alpha1::LabelerStatus;
+pub use v1_alpha1::RegoLibrary;
@@ -5,22 +5,108 @@
 
 use std::collections::BTreeMap;
 
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{Condition, LabelSelector, Time};
 use kube::CustomResource;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
+use crate::validation::{self, ValidationError};
+
+/// `RegoMode` decides how the result of the rego query is interpreted
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub enum RegoMode {
+    /// The query returns a boolean that decides if the resource requires labeling
+    #[default]
+    Condition,
+    /// The query returns an object like `{"labels": {...}, "remove": [...]}`, `labels` are merged
+    /// into `spec.labels` (overriding them) and the `remove` label keys are no longer applied, so
+    /// they're removed from the resource unless another field manager owns them as well. An
+    /// undefined, `null` or `false` result means the resource is skipped.
+    Labels,
+}
+
+/// `ConflictPolicy` decides what happens with labels and annotations that already exist on a
+/// target resource with a different value
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, JsonSchema)]
+pub enum ConflictPolicy {
+    /// Overwrites the existing value
+    #[default]
+    Overwrite,
+    /// Leaves the existing value alone, the rest of the labels are still applied
+    Preserve,
+    /// Leaves the whole resource alone, records it in the status and emits a warning event
+    Fail,
+}
+
 /// `RegoRule` represents the optional rego policy and query for the condition evaluation
 #[derive(Deserialize, Serialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct RegoRule {
-    /// Policy defines the rego policy that will be used in the engine as context for the query
-    #[schemars(length(min = 1, max = 65536))]
+    /// Policy defines the rego policy that will be used in the engine as context for the query,
+    /// can be left empty if the modules are loaded with `policyRef`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    #[schemars(length(max = 65536))]
     pub policy: String,
-    /// Query defines the rego query the engine will evaluate as boolean to decide if the resource
-    /// requires labeling.
-    /// Only use boolean conditions otherwise you will get a runtime error!
+    /// Loads rego modules from a `ConfigMap` alongside the inline `policy`, every key of the
+    /// `ConfigMap` is a module. Editing a `ConfigMap` labeled `stickerbomb.dev/policy` triggers a
+    /// reconciliation of every `Labeler` referencing it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_ref: Option<ConfigMapRef>,
+    /// Static data document available to the policy as `data.*`, lets the same policy be reused
+    /// with different parameters (e.g. `data.registries`)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schemars(schema_with = "crate::validation::free_form_object")]
+    pub data: BTreeMap<String, serde_json::Value>,
+    /// Loads data documents from a `ConfigMap`, every key becomes `data.<key>` with a `.json`
+    /// suffix removed. Values are parsed as json, values that aren't valid json are loaded as
+    /// strings. Editing a `ConfigMap` labeled `stickerbomb.dev/policy` triggers a reconciliation
+    /// of every `Labeler` referencing it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data_ref: Option<ConfigMapRef>,
+    /// Query defines the rego query the engine will evaluate to decide if the resource requires
+    /// labeling.
+    /// In `Condition` mode only use boolean conditions otherwise you will get a runtime error!
     #[schemars(length(min = 1, max = 1024))]
     pub query: String,
+    /// Mode defines how the result of the query is interpreted, defaults to `Condition`
+    #[serde(default)]
+    pub mode: RegoMode,
+}
+
+/// `ConfigMapRef` points to a `ConfigMap` holding rego modules or data documents
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigMapRef {
+    /// Name of the `ConfigMap`
+    #[schemars(length(min = 1, max = 253))]
+    pub name: String,
+    /// Namespace of the `ConfigMap`, defaults to the `Labeler`'s own namespace and is required
+    /// for `ClusterLabeler`s. Only `Labeler`s allowed to target resources cluster-wide may
+    /// reference other namespaces.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(length(min = 1, max = 63))]
+    pub namespace: Option<String>,
+    /// Keys of the `ConfigMap` to load, every key is loaded if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keys: Vec<String>,
+}
+
+/// `TargetResource` describes a kind of resources a `Labeler` applies its labels to
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetResource {
+    /// Describes the target api group of the target resource (e.g., "v1", "apps/v1", "cert-manager.io/v1").
+    /// Use "kubectl api-resources" for a complete list of supported resources.
+    #[schemars(length(min = 1, max = 253))]
+    #[schemars(regex(
+        pattern = r"^([a-z0-9]([a-z0-9.-]*[a-z0-9])?/)?[a-z0-9]([a-z0-9-]*[a-z0-9])?$"
+    ))]
+    pub resource_api: String,
+    /// Describes the target kind of the target resource (e.g., "Pod", "Deployment").
+    /// Use "kubectl api-resources" for a complete list of supported resources.
+    #[schemars(length(min = 1, max = 63))]
+    #[schemars(regex(pattern = r"^[A-Z][a-zA-Z0-9]*$"))]
+    pub resource_kind: String,
 }
 
 /// Spec object for the `Labeler` CRD
@@ -30,6 +116,12 @@ pub struct RegoRule {
 #[kube(kind = "Labeler", group = "stickerbomb.dev", version = "v1alpha1")]
 #[kube(status = "LabelerStatus", shortname = "doc")]
 #[kube(namespaced)]
+#[schemars(extend("x-kubernetes-validations" = [
+    {
+        "rule": crate::validation::TARGET_RULE,
+        "message": "either resourceApi and resourceKind or targets must be set"
+    }
+]))]
 pub struct LabelerSpec {
     /// Describes the target api group of the target resource (e.g., "v1", "apps/v1", "cert-manager.io/v1").
     /// Use "kubectl api-resources" for a complete list of supported resources.
@@ -37,33 +129,344 @@ pub struct LabelerSpec {
     #[schemars(regex(
         pattern = r"^([a-z0-9]([a-z0-9.-]*[a-z0-9])?/)?[a-z0-9]([a-z0-9-]*[a-z0-9])?$"
     ))]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub resource_api: String,
     /// Describes the target kind of the target resource (e.g., "Pod", "Deployment").
     /// Use "kubectl api-resources" for a complete list of supported resources.
     #[schemars(length(min = 1, max = 63))]
     #[schemars(regex(pattern = r"^[A-Z][a-zA-Z0-9]*$"))]
+    #[serde(default, skip_serializing_if = "String::is_empty")]
     pub resource_kind: String,
+    /// Additional kinds of resources to apply the labels to, every entry is discovered and labeled
+    /// separately. Can be used alongside or instead of `resourceApi` and `resourceKind`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<TargetResource>,
+    /// Namespaces to look for target resources in, defaults to the `Labeler`'s own namespace.
+    /// Targeting other namespaces or cluster scoped kinds is only allowed for `Labeler`s living in
+    /// a namespace that the operator allows to create cluster-wide `Labeler`s.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub target_namespaces: Vec<String>,
+    /// Selects the namespaces to look for target resources in by their labels, the matched
+    /// namespaces are added to `targetNamespaces`. An empty selector matches every namespace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace_selector: Option<LabelSelector>,
+    /// Filters the target resources by their labels on the API server, before the rego condition
+    /// is evaluated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<LabelSelector>,
+    /// Filters the target resources by their fields on the API server, before the rego condition
+    /// is evaluated (e.g. `status.phase=Running`).
+    /// Supported fields depend on the target kind, see the
+    /// [field selectors documentation](https://kubernetes.io/docs/concepts/overview/working-with-objects/field-selectors/).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(length(min = 1, max = 1024))]
+    pub field_selector: Option<String>,
     /// Contains the labeling policy described in Rego.
     /// For refference check out [OPA's documentation on rego](https://www.openpolicyagent.org/docs/policy-language).
     /// This operator uses [Microsoft's regorus](https://github.com/microsoft/regorus/tree/main) implementation,
     /// you can write and test some conditions on the [regorus playground](https://anakrish.github.io/regorus-playground/).
+    #[schemars(extend("x-kubernetes-validations" = [
+        {
+            "rule": crate::validation::REGO_POLICY_RULE,
+            "message": "either policy or policyRef must be set"
+        }
+    ]))]
     pub rego: Option<RegoRule>,
-    /// List of labels to apply (must contain at least one label)
+    /// List of labels to apply (must contain at least one label).
+    /// Values can be rendered from the target object with `{{ }}` templates, e.g.
+    /// `{{ metadata.namespace }}` or `{{ spec.containers[0].image | registry }}`, rendered values
+    /// are sanitized and truncated to valid label values.
     #[schemars(length(min = 1))]
+    #[schemars(schema_with = "crate::validation::label_map")]
     pub labels: BTreeMap<String, String>,
+    /// List of annotations to apply alongside the labels (e.g. `linkerd.io/inject: enabled`),
+    /// values support the same templates as `labels`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schemars(schema_with = "crate::validation::qualified_name_map")]
+    pub annotations: BTreeMap<String, String>,
+    /// Removes the applied labels and annotations from resources that no longer match the rego
+    /// condition. Labeled resources are tracked with a `labeler.stickerbomb.dev/<uid>` annotation,
+    /// only resources carrying it are ever pruned.
+    #[serde(default)]
+    pub prune_on_mismatch: bool,
+    /// Decides what happens with labels and annotations that already exist on the target with a
+    /// different value, defaults to `Overwrite`
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+    /// How often the `Labeler` is reconciled (e.g. `30s`, `5m`, `1h30m`), defaults to the
+    /// operator's default interval. Must be between 10s and 24h, a small random jitter is added to
+    /// every interval.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(length(max = 32), regex(pattern = r"^([0-9]+(ms|s|m|h))+$"))]
+    #[schemars(extend("x-kubernetes-validations" = [
+        {
+            "rule": crate::validation::INTERVAL_RULE,
+            "message": "interval must be between 10s and 24h"
+        }
+    ]))]
+    pub interval: Option<String>,
+    /// Computes the changes without patching the target resources, the planned changes are
+    /// recorded in the status instead. Labels applied before dry-run was enabled are still
+    /// removed when the `Labeler` is deleted
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Pauses the `Labeler`, target resources are neither listed nor patched until it's set back
+    /// to `false`
+    #[serde(default)]
+    pub suspend: bool,
+}
+
+impl LabelerSpec {
+    /// Checks the label and annotation keys, the literal label values and the interval, templated
+    /// values are sanitized when they're rendered.
+    ///
+    /// # Errors
+    ///
+    /// This function will return every invalid key and value of the spec.
+    pub fn validate(&self) -> Result<(), ValidationError> {
+        validation::validate(&self.labels, &self.annotations, self.interval.as_deref())
+    }
+
+    /// Returns the `resourceApi` and `resourceKind` pair as a target, `None` if either is empty.
+    #[must_use]
+    pub fn primary_target(&self) -> Option<TargetResource> {
+        (!self.resource_api.is_empty() && !self.resource_kind.is_empty()).then(|| TargetResource {
+            resource_api: self.resource_api.clone(),
+            resource_kind: self.resource_kind.clone(),
+        })
+    }
+
+    /// Returns every target of the `Labeler`, the `resourceApi` and `resourceKind` pair first
+    /// followed by the `targets` list, without duplicates.
+    #[must_use]
+    pub fn targets(&self) -> Vec<TargetResource> {
+        let mut targets: Vec<TargetResource> = Vec::new();
+        for target in self
+            .primary_target()
+            .into_iter()
+            .chain(self.targets.iter().cloned())
+        {
+            if !targets.contains(&target) {
+                targets.push(target);
+            }
+        }
+
+        targets
+    }
+}
+
+/// Spec object for the cluster scoped `ClusterLabeler` CRD, same as the `Labeler`'s spec.
+/// Targets every namespace unless `targetNamespaces` or `namespaceSelector` is set.
+#[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(transparent)]
+#[kube(
+    kind = "ClusterLabeler",
+    group = "stickerbomb.dev",
+    version = "v1alpha1"
+)]
+#[kube(status = "LabelerStatus", shortname = "cdoc")]
+pub struct ClusterLabelerSpec(pub LabelerSpec);
+
+impl std::ops::Deref for ClusterLabelerSpec {
+    type Target = LabelerSpec;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Spec object for the cluster scoped `RegoLibrary` CRD, holds rego modules shared by every
+/// `Labeler` importing its package.
+#[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[kube(kind = "RegoLibrary", group = "stickerbomb.dev", version = "v1alpha1")]
+#[kube(shortname = "rlib")]
+#[kube(printcolumn = r#"{"name":"Package","type":"string","jsonPath":".spec.package"}"#)]
+#[kube(printcolumn = r#"{"name":"Generation","type":"integer","jsonPath":".metadata.generation"}"#)]
+pub struct RegoLibrarySpec {
+    /// Rego package of the library without the `data.` prefix (e.g. `lib.images`), every module
+    /// must declare this package or one of its sub packages. `Labeler`s importing the package, a
+    /// parent or a rule of it get the modules loaded next to their policy.
+    #[schemars(length(min = 1, max = 253))]
+    #[schemars(regex(pattern = r"^[a-zA-Z_][a-zA-Z0-9_]*(\.[a-zA-Z_][a-zA-Z0-9_]*)*$"))]
+    pub package: String,
+    /// Rego modules of the library keyed by their file name (e.g. `images.rego`), editing them
+    /// triggers a reconciliation of every `Labeler` using the library.
+    #[schemars(extend("minProperties" = 1))]
+    pub modules: BTreeMap<String, String>,
 }
 
 /// State object for the `Labeler` CRD
 #[derive(Deserialize, Serialize, Clone, Default, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct LabelerStatus {
-    /// Number of resources that matched `resource_kind`
+    /// Number of resources that matched any of the targets
     #[schemars(range(min = 0))]
     pub resources_matched: i32,
     /// Number of resources labeled in last reconciliation
     #[schemars(range(min = 0))]
     pub resources_labeled: i32,
+    /// Number of resources annotated in last reconciliation
+    #[serde(default)]
+    #[schemars(range(min = 0))]
+    pub resources_annotated: i32,
+    /// Number of resources pruned in last reconciliation because they stopped matching the rego
+    /// condition
+    #[serde(default)]
+    #[schemars(range(min = 0))]
+    pub resources_pruned: i32,
     /// Number of resources failed the rego condition evaluation
     #[schemars(range(min = 0))]
     pub resources_skipped: i32,
+    /// Number of resources left alone in last reconciliation because of conflicting values with
+    /// the `Fail` conflict policy
+    #[serde(default)]
+    #[schemars(range(min = 0))]
+    pub resources_conflicted: i32,
+    /// Number of resources left alone in last reconciliation because a templated value couldn't be
+    /// rendered for them
+    #[serde(default)]
+    #[schemars(range(min = 0))]
+    pub resources_failed: i32,
+    /// Resources with conflicting values from the last reconciliation, capped to the operator's
+    /// status list limit
+    #[serde(default)]
+    pub conflicts: Vec<LabelConflict>,
+    /// Changes a `dryRun` `Labeler` would apply, capped to the operator's status list limit
+    #[serde(default)]
+    pub planned_changes: Vec<PlannedChange>,
+    /// Most recently labeled and pruned resources, newest first, capped to the operator's status
+    /// list limit
+    #[serde(default)]
+    pub recent_changes: Vec<ResourceActivity>,
+    /// Resources left alone in the last reconciliation because templates couldn't be rendered,
+    /// capped to the operator's status list limit. Conflicting values are only reported in
+    /// `conflicts`
+    #[serde(default)]
+    pub failed_resources: Vec<ResourceActivity>,
+    /// Resource counts of the last reconciliation broken down per target
+    #[serde(default)]
+    pub targets: Vec<TargetStatus>,
+    /// Latest observations of the `Labeler`'s state (`Ready`, `Reconciling`, `Degraded`,
+    /// `PolicyError` and `Suspended`)
+    #[serde(default)]
+    pub conditions: Vec<Condition>,
+    /// Generation of the spec the status was computed from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observed_generation: Option<i64>,
+    /// Time of the last reconciliation, successful or not
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_reconcile_time: Option<Time>,
+    /// Resource versions of the `ConfigMap`s the rego policy and data were loaded from, a change
+    /// triggers a reconciliation before the interval passes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_revision: Option<String>,
+    /// Duration of the last successful reconciliation (e.g. `350ms`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_reconcile_duration: Option<String>,
+}
+
+/// Change a `dryRun` `Labeler` would apply to a target resource
+#[derive(Deserialize, Serialize, Clone, Default, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedChange {
+    /// The resource that would be changed
+    pub resource: TargetRef,
+    /// Labels that would be added or changed with their new values
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub labels: BTreeMap<String, String>,
+    /// Label keys that would be removed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed_labels: Vec<String>,
+    /// Annotations that would be added or changed with their new values
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub annotations: BTreeMap<String, String>,
+    /// Annotation keys that would be removed
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub removed_annotations: Vec<String>,
+}
+
+/// What happened to a target resource during a reconciliation
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+pub enum ResourceAction {
+    /// Labels or annotations were added or changed
+    Labeled,
+    /// Labels and annotations were removed because the resource stopped matching
+    Pruned,
+    /// The resource was left alone because a template couldn't be rendered
+    Failed,
+}
+
+/// Action taken on a single target resource
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceActivity {
+    /// The resource the action was taken on
+    pub resource: TargetRef,
+    /// What happened to the resource
+    pub action: ResourceAction,
+    /// Human readable details of the action
+    pub reason: String,
+    /// Time of the action
+    pub timestamp: Time,
+}
+
+/// Resource counts of a single target from the last reconciliation
+#[derive(Deserialize, Serialize, Clone, Default, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetStatus {
+    /// Api group of the target
+    pub resource_api: String,
+    /// Kind of the target
+    pub resource_kind: String,
+    /// Number of resources that matched the target kind
+    #[schemars(range(min = 0))]
+    pub resources_matched: i32,
+    /// Number of resources labeled
+    #[schemars(range(min = 0))]
+    pub resources_labeled: i32,
+    /// Number of resources annotated
+    #[serde(default)]
+    #[schemars(range(min = 0))]
+    pub resources_annotated: i32,
+    /// Number of resources pruned
+    #[serde(default)]
+    #[schemars(range(min = 0))]
+    pub resources_pruned: i32,
+    /// Number of resources left alone because of conflicting values
+    #[serde(default)]
+    #[schemars(range(min = 0))]
+    pub resources_conflicted: i32,
+    /// Number of resources skipped
+    #[schemars(range(min = 0))]
+    pub resources_skipped: i32,
+    /// Number of resources left alone because a templated value couldn't be rendered
+    #[serde(default)]
+    #[schemars(range(min = 0))]
+    pub resources_failed: i32,
+}
+
+/// Reference to a target resource
+#[derive(Deserialize, Serialize, Clone, Default, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetRef {
+    /// Api version of the resource
+    pub api_version: String,
+    /// Kind of the resource
+    pub kind: String,
+    /// Namespace of the resource, empty for cluster scoped resources
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// Name of the resource
+    pub name: String,
+}
+
+/// Target resource that has labels or annotations with a different value than the `Labeler`'s
+#[derive(Deserialize, Serialize, Clone, Default, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelConflict {
+    /// The conflicting resource
+    pub resource: TargetRef,
+    /// Label and annotation keys with conflicting values
+    pub keys: Vec<String>,
 }
@@ -0,0 +1,283 @@
+// Copyright 2026 Stickerbomb Maintainers
+// SPDX-License-Identifier: Apache-2.0
+
+//! v1Beta1 CRD resources
+
+use std::collections::BTreeMap;
+
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{LabelSelector, ObjectMeta};
+use kube::CustomResource;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::v1_alpha1::{self, ConflictPolicy, LabelerStatus, RegoRule, TargetResource};
+
+/// Annotation keeping the `resourceApi`, `resourceKind` and `targets` fields of a `v1alpha1`
+/// object while it's served as `v1beta1`, only set when the `v1beta1` targets don't convert back
+/// to the same fields
+pub const ALPHA_TARGETS_ANNOTATION: &str = "stickerbomb.dev/v1alpha1-targets";
+
+/// Spec object for the `Labeler` CRD
+#[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+#[kube(kind = "Labeler", group = "stickerbomb.dev", version = "v1beta1")]
+#[kube(status = "LabelerStatus", shortname = "doc")]
+#[kube(namespaced)]
+pub struct LabelerSpec {
+    /// Kinds of resources to apply the labels to (must contain at least one target), every entry
+    /// is discovered and labeled separately.
+    #[schemars(length(min = 1))]
+    pub targets: Vec<TargetResource>,
+    /// Namespaces to look for target resources in, defaults to the `Labeler`'s own namespace.
+    /// Targeting other namespaces or cluster scoped kinds is only allowed for `Labeler`s living in
+    /// a namespace that the operator allows to create cluster-wide `Labeler`s.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub target_namespaces: Vec<String>,
+    /// Selects the namespaces to look for target resources in by their labels, the matched
+    /// namespaces are added to `targetNamespaces`. An empty selector matches every namespace.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub namespace_selector: Option<LabelSelector>,
+    /// Filters the target resources by their labels on the API server, before the rego condition
+    /// is evaluated.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<LabelSelector>,
+    /// Filters the target resources by their fields on the API server, before the rego condition
+    /// is evaluated (e.g. `status.phase=Running`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(length(min = 1, max = 1024))]
+    pub field_selector: Option<String>,
+    /// Contains the labeling policy described in Rego.
+    #[schemars(extend("x-kubernetes-validations" = [
+        {
+            "rule": crate::validation::REGO_POLICY_RULE,
+            "message": "either policy or policyRef must be set"
+        }
+    ]))]
+    pub rego: Option<RegoRule>,
+    /// List of labels to apply (must contain at least one label), values support `{{ }}` templates.
+    #[schemars(length(min = 1))]
+    #[schemars(schema_with = "crate::validation::label_map")]
+    pub labels: BTreeMap<String, String>,
+    /// List of annotations to apply alongside the labels, values support the same templates as
+    /// `labels`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    #[schemars(schema_with = "crate::validation::qualified_name_map")]
+    pub annotations: BTreeMap<String, String>,
+    /// Removes the applied labels and annotations from resources that no longer match the rego
+    /// condition.
+    #[serde(default)]
+    pub prune_on_mismatch: bool,
+    /// Decides what happens with labels and annotations that already exist on the target with a
+    /// different value, defaults to `Overwrite`
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+    /// How often the `Labeler` is reconciled (e.g. `30s`, `5m`, `1h30m`), defaults to the
+    /// operator's default interval. Must be between 10s and 24h, a small random jitter is added to
+    /// every interval.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schemars(length(max = 32), regex(pattern = r"^([0-9]+(ms|s|m|h))+$"))]
+    #[schemars(extend("x-kubernetes-validations" = [
+        {
+            "rule": crate::validation::INTERVAL_RULE,
+            "message": "interval must be between 10s and 24h"
+        }
+    ]))]
+    pub interval: Option<String>,
+    /// Computes the changes without patching the target resources, the planned changes are
+    /// recorded in the status instead. Labels applied before dry-run was enabled are still
+    /// removed when the `Labeler` is deleted
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Pauses the `Labeler`, target resources are neither listed nor patched until it's set back
+    /// to `false`
+    #[serde(default)]
+    pub suspend: bool,
+}
+
+/// Spec object for the cluster scoped `ClusterLabeler` CRD, same as the `Labeler`'s spec.
+/// Targets every namespace unless `targetNamespaces` or `namespaceSelector` is set.
+#[derive(CustomResource, Deserialize, Serialize, Clone, Debug, JsonSchema)]
+#[serde(transparent)]
+#[kube(
+    kind = "ClusterLabeler",
+    group = "stickerbomb.dev",
+    version = "v1beta1"
+)]
+#[kube(status = "LabelerStatus", shortname = "cdoc")]
+pub struct ClusterLabelerSpec(pub LabelerSpec);
+
+/// The `resourceApi` and `resourceKind` pair becomes the first target, duplicates are kept so the
+/// object converts back unchanged.
+impl From<v1_alpha1::LabelerSpec> for LabelerSpec {
+    fn from(spec: v1_alpha1::LabelerSpec) -> Self {
+        Self {
+            targets: spec
+                .primary_target()
+                .into_iter()
+                .chain(spec.targets)
+                .collect(),
+            target_namespaces: spec.target_namespaces,
+            namespace_selector: spec.namespace_selector,
+            selector: spec.selector,
+            field_selector: spec.field_selector,
+            rego: spec.rego,
+            labels: spec.labels,
+            annotations: spec.annotations,
+            prune_on_mismatch: spec.prune_on_mismatch,
+            conflict_policy: spec.conflict_policy,
+            interval: spec.interval,
+            dry_run: spec.dry_run,
+            suspend: spec.suspend,
+        }
+    }
+}
+
+/// The first target becomes the `resourceApi` and `resourceKind` pair, the rest stays in `targets`.
+impl From<LabelerSpec> for v1_alpha1::LabelerSpec {
+    fn from(spec: LabelerSpec) -> Self {
+        let mut targets = spec.targets.into_iter();
+        let primary = targets.next();
+
+        Self {
+            resource_api: primary
+                .as_ref()
+                .map(|t| t.resource_api.clone())
+                .unwrap_or_default(),
+            resource_kind: primary.map(|t| t.resource_kind).unwrap_or_default(),
+            targets: targets.collect(),
+            target_namespaces: spec.target_namespaces,
+            namespace_selector: spec.namespace_selector,
+            selector: spec.selector,
+            field_selector: spec.field_selector,
+            rego: spec.rego,
+            labels: spec.labels,
+            annotations: spec.annotations,
+            prune_on_mismatch: spec.prune_on_mismatch,
+            conflict_policy: spec.conflict_policy,
+            interval: spec.interval,
+            dry_run: spec.dry_run,
+            suspend: spec.suspend,
+        }
+    }
+}
+
+/// Target fields of a `v1alpha1` spec, as stored in the round-trip annotation
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+struct AlphaTargets {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    resource_api: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    resource_kind: String,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    targets: Vec<TargetResource>,
+}
+
+impl AlphaTargets {
+    fn of(spec: &v1_alpha1::LabelerSpec) -> Self {
+        Self {
+            resource_api: spec.resource_api.clone(),
+            resource_kind: spec.resource_kind.clone(),
+            targets: spec.targets.clone(),
+        }
+    }
+}
+
+/// Converts the spec to `v1beta1`, the original target fields are kept in the round-trip
+/// annotation when converting back wouldn't restore them.
+fn spec_to_beta(metadata: &mut ObjectMeta, spec: v1_alpha1::LabelerSpec) -> LabelerSpec {
+    let original = AlphaTargets::of(&spec);
+    let spec = LabelerSpec::from(spec);
+    let restored = AlphaTargets::of(&spec.clone().into());
+
+    let annotations = metadata.annotations.get_or_insert_with(BTreeMap::new);
+    if restored == original {
+        annotations.remove(ALPHA_TARGETS_ANNOTATION);
+    } else if let Ok(value) = serde_json::to_string(&original) {
+        annotations.insert(ALPHA_TARGETS_ANNOTATION.to_string(), value);
+    }
+    if annotations.is_empty() {
+        metadata.annotations = None;
+    }
+
+    spec
+}
+
+/// Converts the spec back to `v1alpha1`, the target fields of the round-trip annotation are
+/// restored as long as the `v1beta1` targets haven't changed since.
+fn spec_to_alpha(metadata: &mut ObjectMeta, spec: LabelerSpec) -> v1_alpha1::LabelerSpec {
+    let original = metadata
+        .annotations
+        .as_mut()
+        .and_then(|a| a.remove(ALPHA_TARGETS_ANNOTATION))
+        .and_then(|v| serde_json::from_str::<AlphaTargets>(&v).ok());
+    if metadata
+        .annotations
+        .as_ref()
+        .is_some_and(BTreeMap::is_empty)
+    {
+        metadata.annotations = None;
+    }
+
+    let targets = spec.targets.clone();
+    let converted = v1_alpha1::LabelerSpec::from(spec);
+    let Some(original) = original else {
+        return converted;
+    };
+
+    let restored = v1_alpha1::LabelerSpec {
+        resource_api: original.resource_api,
+        resource_kind: original.resource_kind,
+        targets: original.targets,
+        ..converted.clone()
+    };
+    if LabelerSpec::from(restored.clone()).targets == targets {
+        restored
+    } else {
+        converted
+    }
+}
+
+impl From<v1_alpha1::Labeler> for Labeler {
+    fn from(mut labeler: v1_alpha1::Labeler) -> Self {
+        let spec = spec_to_beta(&mut labeler.metadata, labeler.spec);
+        Self {
+            metadata: labeler.metadata,
+            spec,
+            status: labeler.status,
+        }
+    }
+}
+
+impl From<Labeler> for v1_alpha1::Labeler {
+    fn from(mut labeler: Labeler) -> Self {
+        let spec = spec_to_alpha(&mut labeler.metadata, labeler.spec);
+        Self {
+            metadata: labeler.metadata,
+            spec,
+            status: labeler.status,
+        }
+    }
+}
+
+impl From<v1_alpha1::ClusterLabeler> for ClusterLabeler {
+    fn from(mut labeler: v1_alpha1::ClusterLabeler) -> Self {
+        let spec = spec_to_beta(&mut labeler.metadata, labeler.spec.0);
+        Self {
+            metadata: labeler.metadata,
+            spec: ClusterLabelerSpec(spec),
+            status: labeler.status,
+        }
+    }
+}
+
+impl From<ClusterLabeler> for v1_alpha1::ClusterLabeler {
+    fn from(mut labeler: ClusterLabeler) -> Self {
+        let spec = spec_to_alpha(&mut labeler.metadata, labeler.spec.0);
+        Self {
+            metadata: labeler.metadata,
+            spec: v1_alpha1::ClusterLabelerSpec(spec),
+            status: labeler.status,
+        }
+    }
+}
@@ -0,0 +1,389 @@
+// Copyright 2026 Stickerbomb Maintainers
+// SPDX-License-Identifier: Apache-2.0
+
+//! Label, annotation and interval syntax checks shared by the CRD schema and the operator.
+//!
+//! The API server validates the keys and the literal label values with the CEL rules below, values
+//! containing `{{ }}` templates are skipped as they're sanitized once rendered. Label values are
+//! limited to `MAX_TEMPLATE_LENGTH` characters to keep the rules within the CEL cost budget.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::Duration;
+
+use schemars::{Schema, SchemaGenerator, json_schema};
+
+/// Maximum number of labels or annotations a single `Labeler` may apply
+pub const MAX_KEYS: u32 = 64;
+
+/// CEL rule matching qualified names, an optional DNS subdomain prefix and a name of at most 63
+/// characters separated by a `/`
+pub const QUALIFIED_NAME_RULE: &str = r"self.all(k, k.matches('^([a-z0-9]([-a-z0-9]*[a-z0-9])?(\\.[a-z0-9]([-a-z0-9]*[a-z0-9])?)*/)?[A-Za-z0-9]([-A-Za-z0-9_.]{0,61}[A-Za-z0-9])?$'))";
+
+/// CEL rule matching empty label values or at most 63 alphanumeric characters, `-`, `_` or `.`,
+/// values holding a template are left to the operator
+pub const LABEL_VALUE_RULE: &str = "self.all(k, self[k].contains('{{') || (self[k].size() <= 63 && self[k].matches('^([A-Za-z0-9]([-A-Za-z0-9_.]*[A-Za-z0-9])?)?$')))";
+
+/// Maximum length of a label value in the spec, templated values may be longer than the rendered
+/// value limit
+pub const MAX_TEMPLATE_LENGTH: u32 = 1024;
+
+/// CEL rule limiting the prefix of qualified names to 253 characters
+pub const QUALIFIED_NAME_PREFIX_RULE: &str =
+    "self.all(k, !k.contains('/') || k.split('/')[0].size() <= 253)";
+
+/// CEL rule requiring an inline policy or a `policyRef` on the rego rule
+pub const REGO_POLICY_RULE: &str =
+    "(has(self.policy) && size(self.policy) > 0) || has(self.policyRef)";
+
+/// Shortest reconcile interval a `Labeler` may request
+pub const MIN_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Longest reconcile interval a `Labeler` may request
+pub const MAX_INTERVAL: Duration = Duration::from_hours(24);
+
+/// CEL rule keeping the reconcile interval between `MIN_INTERVAL` and `MAX_INTERVAL`, durations
+/// that overflow fail the rule as well
+pub const INTERVAL_RULE: &str =
+    "duration(self) >= duration('10s') && duration(self) <= duration('24h')";
+
+/// CEL rule requiring the `resourceApi` and `resourceKind` pair or at least one entry in `targets`
+pub const TARGET_RULE: &str = "(has(self.resourceApi) && has(self.resourceKind)) || (has(self.targets) && size(self.targets) > 0)";
+
+/// Schema of label and annotation maps, limits the number of keys and checks them with the
+/// qualified name rules
+#[must_use]
+pub fn qualified_name_map(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "type": "object",
+        "additionalProperties": {"type": "string"},
+        "maxProperties": MAX_KEYS,
+        "x-kubernetes-validations": [
+            {
+                "rule": QUALIFIED_NAME_RULE,
+                "message": "keys must be qualified names (e.g. `example.com/my-label`)"
+            },
+            {
+                "rule": QUALIFIED_NAME_PREFIX_RULE,
+                "message": "key prefixes must be at most 253 characters"
+            }
+        ]
+    })
+}
+
+/// Schema of label maps, checks the keys like `qualified_name_map` and the literal values with
+/// the label value rule
+#[must_use]
+pub fn label_map(generator: &mut SchemaGenerator) -> Schema {
+    let mut schema = qualified_name_map(generator);
+    schema.insert(
+        "additionalProperties".to_string(),
+        serde_json::json!({"type": "string", "maxLength": MAX_TEMPLATE_LENGTH}),
+    );
+    if let Some(serde_json::Value::Array(rules)) = schema.get_mut("x-kubernetes-validations") {
+        rules.push(serde_json::json!({
+            "rule": LABEL_VALUE_RULE,
+            "message": "values must be at most 63 alphanumeric characters, '-', '_' or '.' (e.g. `my-value`)"
+        }));
+    }
+    schema
+}
+
+/// Schema of objects with arbitrary fields, e.g. rego data documents
+#[must_use]
+pub fn free_form_object(_: &mut SchemaGenerator) -> Schema {
+    json_schema!({
+        "type": "object",
+        "x-kubernetes-preserve-unknown-fields": true
+    })
+}
+
+/// Errors found in a `Labeler`'s spec
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ValidationError(pub Vec<String>);
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid spec: {}", self.0.join("; "))
+    }
+}
+
+impl std::error::Error for ValidationError {}
+
+/// Checks the label and annotation keys, the label values that aren't templated and the reconcile
+/// interval.
+///
+/// # Errors
+///
+/// This function will return every invalid key and value found.
+pub fn validate(
+    labels: &BTreeMap<String, String>,
+    annotations: &BTreeMap<String, String>,
+    interval: Option<&str>,
+) -> Result<(), ValidationError> {
+    let mut errors = Vec::new();
+
+    if let Some(interval) = interval
+        && parse_duration(interval).is_none()
+    {
+        errors.push(format!(
+            "interval {interval:?} must be a duration between {}s and {}h (e.g. `5m`)",
+            MIN_INTERVAL.as_secs(),
+            MAX_INTERVAL.as_secs() / 3600
+        ));
+    }
+
+    for (field, map) in [("labels", labels), ("annotations", annotations)] {
+        if map.len() > MAX_KEYS as usize {
+            errors.push(format!(
+                "{field} must not have more than {MAX_KEYS} entries"
+            ));
+        }
+
+        for key in map.keys().filter(|k| !is_qualified_name(k)) {
+            errors.push(format!(
+                "{field} key {key:?} must be a qualified name (e.g. `example.com/my-label`)"
+            ));
+        }
+    }
+
+    for (key, value) in labels {
+        if value.len() > MAX_TEMPLATE_LENGTH as usize {
+            errors.push(format!(
+                "labels value of {key:?} must be at most {MAX_TEMPLATE_LENGTH} characters"
+            ));
+        } else if !value.contains("{{") && !is_label_value(value) {
+            errors.push(format!(
+                "labels value {value:?} of {key:?} must be at most 63 alphanumeric characters, '-', '_' or '.'"
+            ));
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(ValidationError(errors))
+    }
+}
+
+/// Parses durations like `30s`, `5m` or `1h30m`, supported units are `ms`, `s`, `m` and `h`.
+/// Returns `None` for malformed durations and durations outside of `MIN_INTERVAL` and
+/// `MAX_INTERVAL`.
+#[must_use]
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let mut total = Duration::ZERO;
+    let mut rest = value.trim();
+
+    if rest.is_empty() {
+        return None;
+    }
+
+    while !rest.is_empty() {
+        let digits = rest.find(|c: char| !c.is_ascii_digit())?;
+        let amount: u64 = rest[..digits].parse().ok()?;
+        rest = &rest[digits..];
+
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let part = match &rest[..unit_len] {
+            "ms" => Duration::from_millis(amount),
+            "s" => Duration::from_secs(amount),
+            "m" => Duration::from_secs(amount.checked_mul(60)?),
+            "h" => Duration::from_secs(amount.checked_mul(3600)?),
+            _ => return None,
+        };
+
+        total = total.checked_add(part)?;
+        rest = &rest[unit_len..];
+    }
+
+    (MIN_INTERVAL..=MAX_INTERVAL)
+        .contains(&total)
+        .then_some(total)
+}
+
+/// Returns true for an optional DNS subdomain prefix and a name separated by a `/`.
+#[must_use]
+pub fn is_qualified_name(key: &str) -> bool {
+    match key.split_once('/') {
+        Some((prefix, name)) => is_dns_subdomain(prefix) && is_name(name),
+        None => is_name(key),
+    }
+}
+
+/// Returns true for an empty value or a name of at most 63 characters.
+#[must_use]
+pub fn is_label_value(value: &str) -> bool {
+    value.is_empty() || is_name(value)
+}
+
+/// At most 63 alphanumeric characters, `-`, `_` or `.`, starting and ending with an alphanumeric
+fn is_name(name: &str) -> bool {
+    name.len() <= 63
+        && name.starts_with(|c: char| c.is_ascii_alphanumeric())
+        && name.ends_with(|c: char| c.is_ascii_alphanumeric())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Lowercase RFC 1123 subdomain of at most 253 characters
+fn is_dns_subdomain(prefix: &str) -> bool {
+    prefix.len() <= 253
+        && prefix.split('.').all(|part| {
+            part.len() <= 63
+                && part.starts_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+                && part.ends_with(|c: char| c.is_ascii_lowercase() || c.is_ascii_digit())
+                && part
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(k, v)| ((*k).to_string(), (*v).to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(validate(&map(&[("team", "platform")]), &BTreeMap::new(), Some("5m")).is_ok());
+
+        let labels = map(&[
+            ("example.com/team", "platform"),
+            ("image", "{{ spec.containers[0].image }}"),
+            ("-team", "a"),
+            ("Example.com/team", "a"),
+            ("owner", "not a label value"),
+        ]);
+        let annotations = map(&[("example.com/note", "free form text")]);
+
+        let errors = validate(&labels, &annotations, None).unwrap_err().0;
+        assert_eq!(errors.len(), 3, "{errors:?}");
+        assert!(errors[0].contains("\"-team\""));
+        assert!(errors[1].contains("\"Example.com/team\""));
+        assert!(errors[2].contains("\"owner\""));
+    }
+
+    #[test]
+    fn test_validate_max_keys() {
+        let labels: BTreeMap<String, String> = (0..=MAX_KEYS)
+            .map(|i| (format!("key-{i}"), "value".to_string()))
+            .collect();
+
+        let errors = validate(&labels, &BTreeMap::new(), None).unwrap_err().0;
+        assert_eq!(
+            errors,
+            vec![format!("labels must not have more than {MAX_KEYS} entries")]
+        );
+    }
+
+    #[test]
+    fn test_validate_template_length() {
+        let template = format!("{{{{ {} }}}}", "a".repeat(MAX_TEMPLATE_LENGTH as usize));
+
+        let errors = validate(&map(&[("image", &template)]), &BTreeMap::new(), None)
+            .unwrap_err()
+            .0;
+        assert_eq!(
+            errors,
+            vec![format!(
+                "labels value of \"image\" must be at most {MAX_TEMPLATE_LENGTH} characters"
+            )]
+        );
+    }
+
+    #[test]
+    fn test_validate_interval() {
+        let errors = validate(&BTreeMap::new(), &BTreeMap::new(), Some("1ms"))
+            .unwrap_err()
+            .0;
+        assert_eq!(
+            errors,
+            vec!["interval \"1ms\" must be a duration between 10s and 24h (e.g. `5m`)".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("30s"), Some(Duration::from_secs(30)));
+        assert_eq!(parse_duration("1h30m"), Some(Duration::from_mins(90)));
+        assert_eq!(parse_duration("9s1000ms"), Some(Duration::from_secs(10)));
+        assert_eq!(parse_duration("24h"), Some(MAX_INTERVAL));
+        assert_eq!(parse_duration("250ms"), None);
+        assert_eq!(parse_duration("0s"), None);
+        assert_eq!(parse_duration("24h1s"), None);
+        assert_eq!(parse_duration("5124095576030431h"), None);
+        assert_eq!(parse_duration("5"), None);
+        assert_eq!(parse_duration("5d"), None);
+        assert_eq!(parse_duration("m5"), None);
+        assert_eq!(parse_duration(""), None);
+    }
+
+    #[test]
+    fn test_label_map() {
+        let schema = label_map(&mut SchemaGenerator::default());
+
+        assert_eq!(
+            schema.get("additionalProperties"),
+            Some(&serde_json::json!({"type": "string", "maxLength": MAX_TEMPLATE_LENGTH}))
+        );
+        let rules = schema
+            .get("x-kubernetes-validations")
+            .and_then(serde_json::Value::as_array)
+            .map(|rules| rules.iter().map(|r| r["rule"].clone()).collect::<Vec<_>>());
+        assert_eq!(
+            rules,
+            Some(vec![
+                QUALIFIED_NAME_RULE.into(),
+                QUALIFIED_NAME_PREFIX_RULE.into(),
+                LABEL_VALUE_RULE.into()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_is_qualified_name() {
+        let name = "a".repeat(63);
+        assert!(is_qualified_name(&name));
+        assert!(!is_qualified_name(&"a".repeat(64)));
+        assert!(is_qualified_name("my_label.v1"));
+        assert!(!is_qualified_name(""));
+        assert!(!is_qualified_name("team-"));
+
+        let prefix = format!(
+            "{}.{}.{}.{}",
+            "a".repeat(63),
+            "b".repeat(63),
+            "c".repeat(63),
+            "d".repeat(61)
+        );
+        assert_eq!(prefix.len(), 253);
+        assert!(is_qualified_name(&format!("{prefix}/{name}")));
+        assert!(!is_qualified_name(&format!("{prefix}e/team")));
+        assert!(!is_qualified_name(&format!("{}.com/team", "a".repeat(64))));
+        assert!(!is_qualified_name("Example.com/team"));
+        assert!(!is_qualified_name("example.com/"));
+        assert!(!is_qualified_name("/team"));
+        assert!(!is_qualified_name("example..com/team"));
+        assert!(!is_qualified_name("example.com/team/a"));
+    }
+
+    #[test]
+    fn test_is_label_value() {
+        assert!(is_label_value(""));
+        assert!(is_label_value(&"a".repeat(63)));
+        assert!(!is_label_value(&"a".repeat(64)));
+        assert!(is_label_value("v1.2_3-rc"));
+        assert!(!is_label_value("-v1"));
+        assert!(!is_label_value("v1."));
+        assert!(!is_label_value("not a label value"));
+    }
+}
@@ -0,0 +1,279 @@
+// Copyright 2026 Stickerbomb Maintainers
+// SPDX-License-Identifier: Apache-2.0
+
+//! Status conditions reported on `Labeler`s and `ClusterLabeler`s.
+
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{Condition, Time};
+use k8s_openapi::chrono::Utc;
+use stickerbomb_crd::LabelerStatus;
+
+/// The last reconciliation of the current generation succeeded
+pub const READY: &str = "Ready";
+
+/// A new generation of the spec is being applied
+pub const RECONCILING: &str = "Reconciling";
+
+/// The last reconciliation succeeded, but some resources were left alone because of conflicting
+/// values, templates that couldn't be rendered or invalid labels returned by the policy
+pub const DEGRADED: &str = "Degraded";
+
+/// The rego policy can't be compiled or evaluated
+pub const POLICY_ERROR: &str = "PolicyError";
+
+/// The `Labeler` is paused by `spec.suspend`
+pub const SUSPENDED: &str = "Suspended";
+
+/// Reason of the `Ready` condition after a failed reconciliation
+pub const RECONCILIATION_FAILED: &str = "ReconciliationFailed";
+
+/// Reason of the `Ready` condition while the spec doesn't pass validation
+pub const INVALID_SPEC: &str = "InvalidSpec";
+
+/// Sets the condition of the given type, the last transition time is only updated if the status
+/// of an existing condition changes.
+pub fn set_condition(
+    conditions: &mut Vec<Condition>,
+    type_: &str,
+    status: bool,
+    reason: &str,
+    message: impl Into<String>,
+    observed_generation: Option<i64>,
+) {
+    let status = if status { "True" } else { "False" }.to_string();
+
+    let last_transition_time = conditions
+        .iter()
+        .find(|c| c.type_ == type_ && c.status == status)
+        .map_or_else(|| Time(Utc::now()), |c| c.last_transition_time.clone());
+
+    let condition = Condition {
+        type_: type_.to_string(),
+        status,
+        reason: reason.to_string(),
+        message: message.into(),
+        observed_generation,
+        last_transition_time,
+    };
+
+    match conditions.iter_mut().find(|c| c.type_ == type_) {
+        Some(existing) => *existing = condition,
+        None => conditions.push(condition),
+    }
+}
+
+/// Returns the condition of the given type, if any.
+#[must_use]
+pub fn find<'a>(conditions: &'a [Condition], type_: &str) -> Option<&'a Condition> {
+    conditions.iter().find(|c| c.type_ == type_)
+}
+
+/// Sets the conditions of a successful reconciliation from the counters of the status.
+pub fn set_reconciled(status: &mut LabelerStatus, dry_run: bool) {
+    let generation = status.observed_generation;
+    let conditions = &mut status.conditions;
+
+    let (reason, message) = if dry_run {
+        (
+            "DryRunSucceeded",
+            "Planned changes are recorded in the status",
+        )
+    } else {
+        ("ReconciliationSucceeded", "Target resources are labeled")
+    };
+    set_condition(conditions, READY, true, reason, message, generation);
+    set_condition(
+        conditions,
+        RECONCILING,
+        false,
+        "Reconciled",
+        "Current generation is applied",
+        generation,
+    );
+    set_condition(
+        conditions,
+        POLICY_ERROR,
+        false,
+        "PolicyEvaluated",
+        "Rego policy is evaluated",
+        generation,
+    );
+
+    let (conflicted, failed) = (status.resources_conflicted, status.resources_failed);
+    match (conflicted, failed) {
+        (0, 0) => set_condition(
+            conditions,
+            DEGRADED,
+            false,
+            "AllResourcesApplied",
+            "Every matching resource is labeled",
+            generation,
+        ),
+        (_, 0) => set_condition(
+            conditions,
+            DEGRADED,
+            true,
+            "LabelConflict",
+            format!("{conflicted} resources have conflicting values"),
+            generation,
+        ),
+        _ => set_condition(
+            conditions,
+            DEGRADED,
+            true,
+            "ResourcesFailed",
+            format!(
+                "{failed} resources can't be labeled, {conflicted} resources have conflicting values"
+            ),
+            generation,
+        ),
+    }
+}
+
+/// Sets the conditions of a failed reconciliation, `PolicyError` is only touched if the failure is
+/// caused by the rego policy.
+pub fn set_failed(
+    conditions: &mut Vec<Condition>,
+    message: &str,
+    policy_error: bool,
+    generation: Option<i64>,
+) {
+    set_condition(
+        conditions,
+        READY,
+        false,
+        RECONCILIATION_FAILED,
+        message,
+        generation,
+    );
+    set_condition(
+        conditions,
+        RECONCILING,
+        false,
+        RECONCILIATION_FAILED,
+        "Reconciliation is retried after the error interval",
+        generation,
+    );
+
+    if policy_error {
+        set_condition(
+            conditions,
+            POLICY_ERROR,
+            true,
+            "PolicyFailed",
+            message,
+            generation,
+        );
+    }
+}
+
+/// Sets the conditions of a `Labeler` whose spec doesn't pass validation, it's not reconciled
+/// until the spec changes.
+pub fn set_invalid(conditions: &mut Vec<Condition>, message: &str, generation: Option<i64>) {
+    set_condition(conditions, READY, false, INVALID_SPEC, message, generation);
+    set_condition(
+        conditions,
+        RECONCILING,
+        false,
+        INVALID_SPEC,
+        "Waiting for a valid spec",
+        generation,
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_condition() {
+        let mut conditions = Vec::new();
+
+        set_condition(
+            &mut conditions,
+            SUSPENDED,
+            true,
+            "Suspended",
+            "paused",
+            Some(1),
+        );
+        assert_eq!(conditions.len(), 1);
+        assert_eq!(conditions[0].status, "True");
+        let suspended_at = conditions[0].last_transition_time.clone();
+
+        set_condition(
+            &mut conditions,
+            SUSPENDED,
+            true,
+            "Suspended",
+            "still paused",
+            Some(2),
+        );
+        assert_eq!(conditions.len(), 1);
+        assert_eq!(conditions[0].message, "still paused");
+        assert_eq!(conditions[0].observed_generation, Some(2));
+        assert_eq!(conditions[0].last_transition_time, suspended_at);
+
+        set_condition(
+            &mut conditions,
+            SUSPENDED,
+            false,
+            "Active",
+            "resumed",
+            Some(3),
+        );
+        assert_eq!(conditions.len(), 1);
+        assert_eq!(conditions[0].status, "False");
+        assert_eq!(conditions[0].reason, "Active");
+    }
+
+    #[test]
+    fn test_set_reconciled_and_failed() {
+        let mut status = LabelerStatus {
+            resources_conflicted: 2,
+            observed_generation: Some(4),
+            ..LabelerStatus::default()
+        };
+
+        set_reconciled(&mut status, false);
+        assert_eq!(status.conditions.len(), 4);
+        assert_eq!(find(&status.conditions, READY).unwrap().status, "True");
+        assert_eq!(
+            find(&status.conditions, RECONCILING).unwrap().status,
+            "False"
+        );
+        let degraded = find(&status.conditions, DEGRADED).unwrap();
+        assert_eq!(degraded.status, "True");
+        assert_eq!(degraded.reason, "LabelConflict");
+        assert_eq!(degraded.observed_generation, Some(4));
+
+        status.resources_failed = 1;
+        set_reconciled(&mut status, false);
+        let degraded = find(&status.conditions, DEGRADED).unwrap();
+        assert_eq!(degraded.reason, "ResourcesFailed");
+        assert_eq!(
+            degraded.message,
+            "1 resources can't be labeled, 2 resources have conflicting values"
+        );
+
+        set_failed(&mut status.conditions, "Rego Error: boom", true, Some(5));
+        let ready = find(&status.conditions, READY).unwrap();
+        assert_eq!(ready.status, "False");
+        assert_eq!(ready.reason, RECONCILIATION_FAILED);
+        assert_eq!(ready.message, "Rego Error: boom");
+        assert_eq!(
+            find(&status.conditions, POLICY_ERROR).unwrap().status,
+            "True"
+        );
+
+        set_failed(&mut status.conditions, "Kube Error", false, Some(5));
+        assert_eq!(
+            find(&status.conditions, POLICY_ERROR).unwrap().message,
+            "Rego Error: boom"
+        );
+
+        set_invalid(&mut status.conditions, "Invalid spec: bad key", Some(6));
+        let ready = find(&status.conditions, READY).unwrap();
+        assert_eq!(ready.reason, INVALID_SPEC);
+        assert_eq!(ready.observed_generation, Some(6));
+    }
+}
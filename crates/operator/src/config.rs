@@ -0,0 +1,289 @@
+// Copyright 2026 Stickerbomb Maintainers
+// SPDX-License-Identifier: Apache-2.0
+
+//! Operator wide configuration, read from the environment variables set by the helm chart.
+
+use std::env;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use rand::Rng;
+
+use stickerbomb_crd::validation::MIN_INTERVAL;
+pub use stickerbomb_crd::validation::parse_duration;
+
+/// Default time between two reconciliations of a `Labeler`
+const DEFAULT_INTERVAL: Duration = Duration::from_mins(5);
+
+/// Default time before a failed reconciliation is retried
+const DEFAULT_ERROR_INTERVAL: Duration = Duration::from_mins(1);
+
+/// Default maximum jitter added to the requeue intervals, as a fraction of the interval
+const DEFAULT_JITTER: f64 = 0.1;
+
+/// Largest jitter accepted from the environment, keeps the requeue intervals at half of the
+/// interval or more
+const MAX_JITTER: f64 = 0.5;
+
+/// Default maximum number of entries in each list of a `Labeler`'s status
+const DEFAULT_STATUS_LIST_LIMIT: usize = 20;
+
+/// Operator wide configuration
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Namespaces whose `Labeler`s may target resources outside of their own namespace, including
+    /// cluster scoped resources. A `*` entry allows every namespace.
+    pub cluster_wide_namespaces: Vec<String>,
+    /// Webhook server configuration, the webhooks are disabled if it's not set
+    pub webhook: Option<WebhookConfig>,
+    /// Time between two reconciliations of `Labeler`s without `spec.interval`
+    pub default_interval: Duration,
+    /// Time before a failed reconciliation is retried
+    pub error_interval: Duration,
+    /// Maximum jitter added to or subtracted from the requeue intervals, as a fraction of the
+    /// interval (between 0 and 0.5)
+    pub jitter: f64,
+    /// Maximum number of entries in each list of a `Labeler`'s status (conflicts, planned changes,
+    /// recent changes and failed resources), keeps the status far from the object size limit
+    pub status_list_limit: usize,
+    /// Watches `ConfigMap`s labeled with the policy label to reconcile the `Labeler`s referencing
+    /// them right away, otherwise changes are picked up on the next reconciliation
+    pub watch_policy_config_maps: bool,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            cluster_wide_namespaces: Vec::new(),
+            webhook: None,
+            default_interval: DEFAULT_INTERVAL,
+            error_interval: DEFAULT_ERROR_INTERVAL,
+            jitter: DEFAULT_JITTER,
+            status_list_limit: DEFAULT_STATUS_LIST_LIMIT,
+            watch_policy_config_maps: true,
+        }
+    }
+}
+
+/// Configuration of the https server serving the webhooks
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WebhookConfig {
+    /// Directory holding the `tls.crt`, `tls.key` and `ca.crt` files of the serving certificate
+    pub cert_dir: PathBuf,
+}
+
+impl Config {
+    /// Reads the configuration from the environment:
+    /// - `CLUSTER_WIDE_NAMESPACES`: comma separated list of namespaces allowed to create
+    ///   cluster-wide `Labeler`s
+    /// - `WEBHOOK_CERT_DIR`: enables the webhook server if set
+    /// - `DEFAULT_INTERVAL`: reconcile interval of `Labeler`s without `spec.interval` (e.g. `5m`)
+    /// - `ERROR_INTERVAL`: retry interval of failed reconciliations (e.g. `1m`)
+    /// - `REQUEUE_JITTER`: maximum jitter of the intervals as a fraction up to `0.5` (e.g. `0.1`)
+    /// - `STATUS_LIST_LIMIT`: maximum number of entries in each list of the status (e.g. `20`)
+    /// - `WATCH_POLICY_CONFIGMAPS`: `false` disables the watch of policy `ConfigMap`s
+    ///
+    /// Missing or invalid values fall back to their defaults, intervals must be between 10s and
+    /// 24h like the ones of the `Labeler`s.
+    #[must_use]
+    pub fn from_env() -> Self {
+        let webhook = env::var("WEBHOOK_CERT_DIR")
+            .ok()
+            .map(|cert_dir| WebhookConfig {
+                cert_dir: PathBuf::from(cert_dir),
+            });
+
+        let interval = |name: &str, default: Duration| {
+            env::var(name)
+                .ok()
+                .and_then(|v| parse_duration(&v))
+                .unwrap_or(default)
+        };
+
+        Self {
+            cluster_wide_namespaces: env::var("CLUSTER_WIDE_NAMESPACES")
+                .map(|v| parse_list(&v))
+                .unwrap_or_default(),
+            webhook,
+            default_interval: interval("DEFAULT_INTERVAL", DEFAULT_INTERVAL),
+            error_interval: interval("ERROR_INTERVAL", DEFAULT_ERROR_INTERVAL),
+            jitter: env::var("REQUEUE_JITTER")
+                .ok()
+                .and_then(|v| v.trim().parse::<f64>().ok())
+                .filter(|j| (0.0..=MAX_JITTER).contains(j))
+                .unwrap_or(DEFAULT_JITTER),
+            status_list_limit: env::var("STATUS_LIST_LIMIT")
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(DEFAULT_STATUS_LIST_LIMIT),
+            watch_policy_config_maps: env::var("WATCH_POLICY_CONFIGMAPS")
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(true),
+        }
+    }
+
+    /// Adds a random jitter of up to `jitter` fraction of the interval in both directions, so
+    /// requeued objects don't hit the API server at the same time. The jittered interval never
+    /// drops below the shortest interval a `Labeler` may request.
+    #[must_use]
+    pub fn jittered(&self, interval: Duration) -> Duration {
+        if self.jitter <= 0.0 {
+            return interval;
+        }
+
+        let factor = rand::rng().random_range(1.0 - self.jitter..=1.0 + self.jitter);
+        Duration::try_from_secs_f64(interval.as_secs_f64() * factor)
+            .unwrap_or(interval)
+            .max(MIN_INTERVAL)
+    }
+
+    /// Returns true if `Labeler`s in the namespace may target resources cluster-wide.
+    #[must_use]
+    pub fn allows_cluster_wide(&self, namespace: &str) -> bool {
+        self.cluster_wide_namespaces
+            .iter()
+            .any(|ns| ns == "*" || ns == namespace)
+    }
+}
+
+/// Splits a comma separated list, dropping empty entries.
+fn parse_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_env_cluster_wide_namespaces() {
+        temp_env::with_var(
+            "CLUSTER_WIDE_NAMESPACES",
+            Some("stickerbomb, platform,,"),
+            || {
+                let config = Config::from_env();
+                assert_eq!(
+                    config.cluster_wide_namespaces,
+                    vec!["stickerbomb", "platform"]
+                );
+                assert!(config.allows_cluster_wide("platform"));
+                assert!(!config.allows_cluster_wide("team-a"));
+            },
+        );
+    }
+
+    #[test]
+    fn test_from_env_defaults() {
+        temp_env::with_vars_unset(["CLUSTER_WIDE_NAMESPACES", "WEBHOOK_CERT_DIR"], || {
+            let config = Config::from_env();
+            assert!(config.cluster_wide_namespaces.is_empty());
+            assert!(!config.allows_cluster_wide("default"));
+            assert_eq!(config.webhook, None);
+        });
+    }
+
+    #[test]
+    fn test_from_env_webhook() {
+        temp_env::with_vars([("WEBHOOK_CERT_DIR", Some("/certs"))], || {
+            assert_eq!(
+                Config::from_env().webhook,
+                Some(WebhookConfig {
+                    cert_dir: PathBuf::from("/certs"),
+                })
+            );
+        });
+    }
+
+    #[test]
+    fn test_from_env_intervals() {
+        temp_env::with_vars(
+            [
+                ("DEFAULT_INTERVAL", Some("1h")),
+                ("ERROR_INTERVAL", Some("invalid")),
+                ("REQUEUE_JITTER", Some("0.25")),
+            ],
+            || {
+                let config = Config::from_env();
+                assert_eq!(config.default_interval, Duration::from_hours(1));
+                assert_eq!(config.error_interval, DEFAULT_ERROR_INTERVAL);
+                assert!((config.jitter - 0.25).abs() < f64::EPSILON);
+            },
+        );
+        temp_env::with_var("REQUEUE_JITTER", Some("0.9"), || {
+            assert!((Config::from_env().jitter - DEFAULT_JITTER).abs() < f64::EPSILON);
+        });
+    }
+
+    #[test]
+    fn test_from_env_status_list_limit() {
+        temp_env::with_var("STATUS_LIST_LIMIT", Some("50"), || {
+            assert_eq!(Config::from_env().status_list_limit, 50);
+        });
+        temp_env::with_var("STATUS_LIST_LIMIT", Some("-1"), || {
+            assert_eq!(
+                Config::from_env().status_list_limit,
+                DEFAULT_STATUS_LIST_LIMIT
+            );
+        });
+    }
+
+    #[test]
+    fn test_from_env_watch_policy_config_maps() {
+        temp_env::with_var("WATCH_POLICY_CONFIGMAPS", Some("false"), || {
+            assert!(!Config::from_env().watch_policy_config_maps);
+        });
+        temp_env::with_var("WATCH_POLICY_CONFIGMAPS", Some("invalid"), || {
+            assert!(Config::from_env().watch_policy_config_maps);
+        });
+    }
+
+    #[test]
+    fn test_jittered() {
+        let interval = Duration::from_mins(10);
+        let config = Config {
+            jitter: 0.1,
+            ..Config::default()
+        };
+
+        for _ in 0..100 {
+            let jittered = config.jittered(interval);
+            assert!(jittered >= Duration::from_mins(9) && jittered <= Duration::from_mins(11));
+        }
+
+        let config = Config {
+            jitter: 0.0,
+            ..Config::default()
+        };
+        assert_eq!(config.jittered(interval), interval);
+
+        let config = Config {
+            jitter: 1.0,
+            ..Config::default()
+        };
+        assert!(config.jittered(Duration::MAX) <= Duration::MAX);
+
+        // The shortest interval stays above the minimum even with the largest jitter
+        let config = Config {
+            jitter: MAX_JITTER,
+            ..Config::default()
+        };
+        for _ in 0..100 {
+            assert!(config.jittered(MIN_INTERVAL) >= MIN_INTERVAL);
+        }
+    }
+
+    #[test]
+    fn test_allows_cluster_wide_wildcard() {
+        let config = Config {
+            cluster_wide_namespaces: vec!["*".to_string()],
+            ..Config::default()
+        };
+        assert!(config.allows_cluster_wide("team-a"));
+    }
+}
@@ -3,28 +3,296 @@
 
 //! Controller components for the k8s operator.
 
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Debug;
+use std::hash::{Hash, Hasher};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
-use crate::{Error, Result, telemetry};
+use crate::conditions::{self, READY, RECONCILING, SUSPENDED};
+use crate::config::{self, Config};
+use crate::{Error, Result, telemetry, template};
+use crate::{policy, targets};
 use futures::StreamExt;
-use k8s_openapi::api::core::v1::ObjectReference;
+use k8s_openapi::api::core::v1::{ConfigMap, Namespace, ObjectReference};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::{ManagedFieldsEntry, Time};
 use k8s_openapi::chrono::Utc;
 use kube::api::{DynamicObject, ListParams, ObjectMeta, Patch, PatchParams};
-use kube::core::gvk::GroupVersion;
+use kube::core::gvk::{GroupVersion, GroupVersionKind};
+use kube::core::{PartialObjectMeta, Selector, SelectorExt};
+use kube::discovery::{ApiCapabilities, ApiResource, Scope};
 use kube::runtime::Controller;
 use kube::runtime::events::{Event, EventType, Recorder};
-use kube::runtime::watcher::Config;
+use kube::runtime::finalizer::{Event as FinalizerEvent, finalizer};
+use kube::runtime::reflector::{self, Store};
+use kube::runtime::{WatchStreamExt, metadata_watcher, watcher};
 use kube::{Api, Resource, ResourceExt, discovery};
 use kube::{Client, runtime::controller::Action};
 use regorus::Engine;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
 use serde_json::json;
-use stickerbomb_crd::v1_alpha1::RegoRule;
-use stickerbomb_crd::{Labeler, LabelerStatus};
+use stickerbomb_crd::v1_alpha1::{
+    ConflictPolicy, LabelConflict, LabelerSpec, PlannedChange, RegoMode, RegoRule, ResourceAction,
+    ResourceActivity, TargetRef, TargetResource, TargetStatus,
+};
+use stickerbomb_crd::{ClusterLabeler, Labeler, LabelerStatus, RegoLibrary, validation};
 use tokio::sync::{RwLock, watch};
 use tracing::{Span, debug, error, field, info, instrument, warn};
 
-use crate::diagnostics::Diagnostics;
+use crate::diagnostics::{Diagnostics, LabelerDiagnostics};
+
+/// Finalizer placed on every `Labeler`, it makes sure the applied labels get removed from the
+/// target resources before the `Labeler` itself is deleted.
+pub const LABELER_FINALIZER: &str = "labelers.stickerbomb.dev/cleanup";
+
+/// Prefix of the annotation that marks a target resource as labeled by a `Labeler`, the `Labeler`'s
+/// uid is appended to it. Only used when `pruneOnMismatch` is enabled.
+pub const OWNERSHIP_ANNOTATION_PREFIX: &str = "labeler.stickerbomb.dev/";
+
+/// Prefix of the field manager every `Labeler` applies its labels and annotations with, followed by
+/// the `Labeler`'s namespace (if it has one) and name.
+pub const FIELD_MANAGER_PREFIX: &str = "stickerbomb";
+
+/// Maximum length of a field manager accepted by the API server
+const MAX_FIELD_MANAGER_LENGTH: usize = 128;
+
+/// Common interface of the namespaced `Labeler` and the cluster scoped `ClusterLabeler`, both kinds
+/// are reconciled by the same logic.
+pub trait LabelerResource:
+    Resource<DynamicType = ()> + Clone + Debug + DeserializeOwned + Serialize + Send + Sync + 'static
+{
+    /// Returns the labeling spec of the object
+    fn spec(&self) -> &LabelerSpec;
+
+    /// Returns the last reported status of the object
+    fn status(&self) -> Option<&LabelerStatus>;
+
+    /// Returns an api client scoped to the object's own namespace (if it has one)
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a namespaced object is missing its namespace.
+    fn api(&self, client: Client) -> Result<Api<Self>>;
+}
+
+impl LabelerResource for Labeler {
+    fn spec(&self) -> &LabelerSpec {
+        &self.spec
+    }
+
+    fn status(&self) -> Option<&LabelerStatus> {
+        self.status.as_ref()
+    }
+
+    fn api(&self, client: Client) -> Result<Api<Self>> {
+        let ns = self
+            .namespace()
+            .ok_or_else(|| Error::from("Unable to get source namespace".to_string()))?;
+
+        Ok(Api::namespaced(client, &ns))
+    }
+}
+
+impl LabelerResource for ClusterLabeler {
+    fn spec(&self) -> &LabelerSpec {
+        &self.spec
+    }
+
+    fn status(&self) -> Option<&LabelerStatus> {
+        self.status.as_ref()
+    }
+
+    fn api(&self, client: Client) -> Result<Api<Self>> {
+        Ok(Api::all(client))
+    }
+}
+
+/// Labels and annotations a `Labeler` manages on its target resources
+#[derive(Clone, Debug, Default, PartialEq)]
+struct Stickers {
+    labels: BTreeMap<String, String>,
+    annotations: BTreeMap<String, String>,
+    /// Field manager the labels and annotations are applied with
+    manager: String,
+}
+
+/// Labels computed by a rego policy running in `Labels` mode
+#[derive(Deserialize, Debug, Default, PartialEq)]
+struct PolicyLabels {
+    #[serde(default)]
+    labels: BTreeMap<String, String>,
+    #[serde(default)]
+    remove: BTreeSet<String>,
+}
+
+impl Stickers {
+    /// Collects the stickers of a `Labeler` including the ownership annotation if pruning is
+    /// enabled.
+    fn from_labeler<K: LabelerResource>(labeler: &K, uid: &str) -> Self {
+        let mut annotations = labeler.spec().annotations.clone();
+
+        if labeler.spec().prune_on_mismatch {
+            annotations.insert(ownership_annotation(uid), labeler.name_any());
+        }
+
+        Self {
+            labels: labeler.spec().labels.clone(),
+            annotations,
+            manager: field_manager(labeler, uid),
+        }
+    }
+
+    /// Returns the label and annotation keys that already exist on the resource with a different
+    /// value, keys applied by the `Labeler`'s own field manager never conflict.
+    fn conflicting(&self, meta: &ObjectMeta) -> (Vec<String>, Vec<String>) {
+        let (managed_labels, managed_annotations) = managed_keys(meta, &self.manager);
+
+        let labels = conflicting_keys(meta.labels.as_ref(), &self.labels)
+            .filter(|k| !managed_labels.contains(*k))
+            .cloned()
+            .collect();
+        let annotations = conflicting_keys(meta.annotations.as_ref(), &self.annotations)
+            .filter(|k| !managed_annotations.contains(*k))
+            .cloned()
+            .collect();
+
+        (labels, annotations)
+    }
+
+    /// Returns the label and annotation keys that already exist on the resource with a different
+    /// value.
+    fn conflicts(&self, meta: &ObjectMeta) -> Vec<String> {
+        let (labels, annotations) = self.conflicting(meta);
+        labels.into_iter().chain(annotations).collect()
+    }
+
+    /// Drops every label and annotation that already exists on the resource with a different
+    /// value.
+    fn preserve_existing(mut self, meta: &ObjectMeta) -> Self {
+        let (labels, annotations) = self.conflicting(meta);
+
+        self.labels.retain(|k, _| !labels.contains(k));
+        self.annotations.retain(|k, _| !annotations.contains(k));
+
+        self
+    }
+
+    /// Merges the labels computed by a rego policy into the stickers, policy labels override the
+    /// ones from the spec and removals win over both. Removed keys are simply not applied anymore.
+    /// Policy label values are sanitized like rendered templates, templated ones are left to
+    /// [`Stickers::render`].
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the policy returns an invalid label key or a value
+    /// that can't be turned into a valid label value.
+    fn with_policy(&self, policy: PolicyLabels) -> Result<Self> {
+        let mut labels = self.labels.clone();
+
+        for (key, value) in policy.labels {
+            if !validation::is_qualified_name(&key) {
+                return Err(Error::InvalidPolicyLabel {
+                    key,
+                    reason: "not a valid label key".to_string(),
+                });
+            }
+
+            let value = if template::is_template(&value) {
+                value
+            } else {
+                template::sanitize_label_value(&value).map_err(|e| Error::InvalidPolicyLabel {
+                    key: key.clone(),
+                    reason: e.to_string(),
+                })?
+            };
+
+            labels.insert(key, value);
+        }
+
+        labels.retain(|k, _| !policy.remove.contains(k));
+
+        Ok(Self {
+            labels,
+            annotations: self.annotations.clone(),
+            manager: self.manager.clone(),
+        })
+    }
+
+    /// Renders every templated label and annotation value against the target resource, rendered
+    /// label values are sanitized to fit the label value syntax.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if a template can't be rendered for the resource.
+    fn render(&self, resource: &DynamicObject) -> Result<Self> {
+        let templated = self
+            .labels
+            .values()
+            .chain(self.annotations.values())
+            .any(|v| template::is_template(v));
+
+        if !templated {
+            return Ok(self.clone());
+        }
+
+        let input = serde_json::to_value(resource)?;
+
+        let render_map = |map: &BTreeMap<String, String>, sanitize: bool| {
+            map.iter()
+                .map(|(key, value)| {
+                    if !template::is_template(value) {
+                        return Ok((key.clone(), value.clone()));
+                    }
+
+                    template::render(value, &input)
+                        .and_then(|v| {
+                            if sanitize {
+                                template::sanitize_label_value(&v)
+                            } else {
+                                Ok(v)
+                            }
+                        })
+                        .map(|v| (key.clone(), v))
+                        .map_err(|source| Error::TemplateError {
+                            key: key.clone(),
+                            source,
+                        })
+                })
+                .collect::<Result<BTreeMap<_, _>>>()
+        };
+
+        Ok(Self {
+            labels: render_map(&self.labels, true)?,
+            annotations: render_map(&self.annotations, false)?,
+            manager: self.manager.clone(),
+        })
+    }
+}
+
+/// Change planned for a single target resource, holds the patch to send
+#[derive(Debug, PartialEq)]
+enum Change {
+    /// Apply the `Labeler`'s stickers with its field manager
+    Label(serde_json::Value),
+    /// Apply the `Labeler`'s stickers that are already present to take them over with its field
+    /// manager
+    Adopt(serde_json::Value),
+    /// Remove the stickers from a resource that stopped matching the rego condition
+    Prune(serde_json::Value),
+    /// Leave the resource alone because of the conflicting keys
+    Conflict(Vec<String>),
+}
+
+/// Namespaces a `Labeler` looks for target resources in
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum TargetScope {
+    /// Every namespace, or the target kind is cluster scoped
+    Cluster,
+    /// Only the listed namespaces
+    Namespaces(BTreeSet<String>),
+}
 
 /// Context for our reconciler
 #[derive(Clone)]
@@ -35,8 +303,8 @@ pub struct Context {
     pub diagnostics: Arc<RwLock<Diagnostics>>,
     /// Kubernetes event emitter
     pub recorder: Recorder,
-    /// In-memory status for the Labeler
-    pub state: Arc<RwLock<LabelerStatus>>,
+    /// Operator wide configuration
+    pub config: Config,
 }
 
 /// Holds the state of the whole application
@@ -44,9 +312,20 @@ pub struct Context {
 pub struct State {
     /// Atomic lock for kubernetes diagnostics
     pub diagnostics: Arc<RwLock<Diagnostics>>,
+    /// Operator wide configuration
+    pub config: Config,
 }
 
 impl State {
+    /// Creates a new application state with the given configuration
+    #[must_use]
+    pub fn new(config: Config) -> Self {
+        Self {
+            config,
+            ..Self::default()
+        }
+    }
+
     /// Getter for diagnostics with read lock
     pub async fn diagnostics(&self) -> Diagnostics {
         self.diagnostics.read().await.clone()
@@ -54,81 +333,183 @@ impl State {
 
     /// Converts the application state to controller context
     pub async fn to_ctrl_context(&self, client: Client) -> Arc<Context> {
-        let state = Arc::new(RwLock::new(LabelerStatus {
-            resources_skipped: 0,
-            resources_labeled: 0,
-            resources_matched: 0,
-        }));
-
         Arc::new(Context {
             recorder: self.diagnostics.read().await.recorder(client.clone()),
             client: client.clone(),
-            state,
+            config: self.config.clone(),
             diagnostics: self.diagnostics.clone(),
         })
     }
 }
 
-/// Instantiates and runs a new controller with it's dependencies from the current shared state.
+/// Instantiates and runs the `Labeler` and `ClusterLabeler` controllers with their dependencies from
+/// the current shared state.
 #[instrument(skip(state, client))]
-pub async fn run(client: Client, state: State, mut leader_rx: watch::Receiver<bool>) {
+pub async fn run(client: Client, state: State, leader_rx: watch::Receiver<bool>) {
     info!("initializing stickerbomb controller");
 
     let labelers = Api::<Labeler>::all(client.clone());
-    if let Err(e) = labelers.list(&ListParams::default().limit(1)).await {
+    let cluster_labelers = Api::<ClusterLabeler>::all(client.clone());
+    ensure_crd_installed(&labelers).await;
+    ensure_crd_installed(&cluster_labelers).await;
+    ensure_crd_installed(&Api::<RegoLibrary>::all(client.clone())).await;
+
+    let mut leader = leader_rx.clone();
+    loop {
+        if leader.wait_for(|&is_leader| is_leader).await.is_err() {
+            break;
+        }
+        info!("acquired leadership, starting controllers");
+
+        let ctx = state.to_ctrl_context(client.clone()).await;
+
+        let labeler_controller = controller(labelers.clone(), &client, &ctx.config);
+        let cluster_labeler_controller = controller(cluster_labelers.clone(), &client, &ctx.config);
+        // Changed target objects are labeled by the `Labeler`s targeting them
+        let labeler_triggers = targets::Triggers::new(labeler_controller.store());
+        let cluster_labeler_triggers = targets::Triggers::new(cluster_labeler_controller.store());
+
+        tokio::join!(
+            run_controller(labeler_controller, ctx.clone(), leader_rx.clone()),
+            run_controller(cluster_labeler_controller, ctx.clone(), leader_rx.clone()),
+            targets::watch(
+                labeler_triggers,
+                cluster_labeler_triggers,
+                ctx,
+                leader_rx.clone()
+            ),
+        );
+
+        info!("controller shutdown complete");
+    }
+}
+
+/// Exits the process if the kind can't be listed.
+async fn ensure_crd_installed<K>(api: &Api<K>)
+where
+    K: Resource<DynamicType = ()> + Clone + Debug + DeserializeOwned,
+{
+    if let Err(e) = api.list(&ListParams::default().limit(1)).await {
         error!(
             error = %e,
-            "failed to list labeler resources, CRD may not be installed"
+            kind = %K::kind(&()),
+            "failed to list resources, CRD may not be installed"
         );
         std::process::exit(1);
     }
+}
 
-    loop {
-        if leader_rx.wait_for(|&is_leader| is_leader).await.is_err() {
-            break;
-        }
-        info!("acquired leadership, starting controller");
+/// Builds the controller of the kind, changes of `ConfigMap`s referenced by `policyRef` or
+/// `dataRef` and of imported `RegoLibrary`s trigger a reconciliation of the referencing objects.
+/// Only the metadata of `ConfigMap`s labeled with `policy::POLICY_LABEL` is watched, unless the
+/// watch is disabled.
+/// Changes of the objects themselves only trigger a reconciliation if `spec_changes` changed, so
+/// status updates don't requeue the object.
+fn controller<K: LabelerResource>(api: Api<K>, client: &Client, config: &Config) -> Controller<K> {
+    let (store, writer) = reflector::store();
+    let stream = watcher(api, watcher::Config::default().any_semantic())
+        .default_backoff()
+        .reflect(writer)
+        .applied_objects()
+        .predicate_filter(|labeler: &K| Some(spec_changes(labeler)));
+    let controller = Controller::for_stream(stream, store);
+    let labelers = controller.store();
+    let importers = labelers.clone();
+
+    let controller = controller.watches(
+        Api::<RegoLibrary>::all(client.clone()),
+        watcher::Config::default().any_semantic(),
+        move |library| policy::importing(&importers, &library),
+    );
 
-        let mut shutdown_rx = leader_rx.clone();
-        let shutdown = async move {
-            let _ = shutdown_rx.wait_for(|&is_leader| !is_leader).await;
-        };
+    if !config.watch_policy_config_maps {
+        return controller;
+    }
 
-        Controller::new(labelers.clone(), Config::default().any_semantic())
-            .graceful_shutdown_on(shutdown)
-            .run(
-                reconcile,
-                error_policy,
-                state.to_ctrl_context(client.clone()).await,
-            )
-            .filter_map(|x| async move { std::result::Result::ok(x) })
-            .for_each(|_| futures::future::ready(()))
-            .await;
+    let config = config.clone();
+    let config_maps = metadata_watcher(
+        Api::<ConfigMap>::all(client.clone()),
+        watcher::Config::default()
+            .labels(policy::POLICY_LABEL)
+            .any_semantic(),
+    )
+    .default_backoff()
+    .touched_objects();
 
-        info!("controller shutdown complete");
-    }
+    controller.watches_stream(config_maps, move |config_map| {
+        policy::referencing(&labelers, &config_map, &config)
+    })
+}
+
+/// Hashes the parts of a `Labeler` that require a reconciliation: the generation, the finalizers
+/// and whether it's being deleted.
+fn spec_changes<K: Resource>(labeler: &K) -> u64 {
+    let meta = labeler.meta();
+    let mut hasher = DefaultHasher::new();
+    meta.generation.hash(&mut hasher);
+    meta.finalizers.hash(&mut hasher);
+    meta.deletion_timestamp.is_some().hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Runs the controller until the leadership is lost.
+async fn run_controller<K: LabelerResource>(
+    controller: Controller<K>,
+    ctx: Arc<Context>,
+    mut leader_rx: watch::Receiver<bool>,
+) {
+    let shutdown = async move {
+        let _ = leader_rx.wait_for(|&is_leader| !is_leader).await;
+    };
+
+    controller
+        .graceful_shutdown_on(shutdown)
+        .run(reconcile::<K>, error_policy::<K>, ctx)
+        .filter_map(|x| async move { std::result::Result::ok(x) })
+        .for_each(|_| futures::future::ready(()))
+        .await;
 }
 
-/// Main reconcile loop for the operator, recalls reconcile every 5 mins and processes a `Labeler`
-/// instance with it's `Context`.
-/// It fetches every resource that matches the `Labeler`'s kind and api, runs the rego condition if
-/// specified and patches the resource labels if needed.
+/// Main reconcile loop for the operator, processes a `Labeler` instance with it's `Context`.
+/// Wraps the labeling logic in a finalizer, so deleted `Labeler`s get their labels cleaned up from
+/// the target resources before they are removed from the cluster.
 ///
 /// # Errors
 ///
 /// This function will return an error if any of the k8s api calls fail, see `crate::Error` for
 /// explicit error details.
 #[instrument(skip(doc, ctx), fields(
+    labeler_kind = %K::kind(&()),
     labeler_name = %doc.name_any(),
     labeler_namespace = doc.namespace().as_deref(),
     labeler_uid = tracing::field::Empty,
-    resource_api = %doc.spec.resource_api,
-    resource_kind = %doc.spec.resource_kind,
-    has_rego_policy = doc.spec.rego.is_some(),
+    target_count = doc.spec().targets().len(),
+    has_rego_policy = doc.spec().rego.is_some(),
 ))]
+async fn reconcile<K: LabelerResource>(doc: Arc<K>, ctx: Arc<Context>) -> Result<Action> {
+    let labelers = doc.api(ctx.client.clone())?;
+
+    finalizer(&labelers, LABELER_FINALIZER, doc, |event| async {
+        match event {
+            FinalizerEvent::Apply(doc) => apply_labels(doc, ctx.clone()).await,
+            FinalizerEvent::Cleanup(doc) => cleanup_labels(doc, ctx.clone()).await,
+        }
+    })
+    .await
+    .map_err(|e| Error::FinalizerError(Box::new(e)))
+}
+
+/// Recalls every `spec.interval` (or the operator's default interval) and fetches every resource
+/// that matches any of the `Labeler`'s targets, runs the rego condition if specified and patches
+/// the resource labels if needed.
+///
+/// # Errors
+///
+/// This function will return an error if any of the k8s api calls fail, see `crate::Error` for
+/// explicit error details.
 #[allow(clippy::needless_pass_by_value)]
-async fn reconcile(doc: Arc<Labeler>, ctx: Arc<Context>) -> Result<Action> {
-    let name = doc.name_any();
+async fn apply_labels<K: LabelerResource>(doc: Arc<K>, ctx: Arc<Context>) -> Result<Action> {
+    let doc = doc.as_ref();
     let oref = doc.object_ref(&());
     let uid = oref
         .uid
@@ -142,21 +523,238 @@ async fn reconcile(doc: Arc<Labeler>, ctx: Arc<Context>) -> Result<Action> {
 
     Span::current().record("labeler_uid", uid.as_str());
 
+    let started = Instant::now();
+    let generation = doc.meta().generation;
+    let previous = doc.status().cloned().unwrap_or_default();
+    let mut conditions = previous.conditions.clone();
+
+    if doc.spec().suspend {
+        info!("labeler is suspended, skipping reconciliation");
+
+        let suspended =
+            conditions::find(&conditions, SUSPENDED).is_some_and(|c| c.status == "True");
+        conditions::set_condition(
+            &mut conditions,
+            SUSPENDED,
+            true,
+            "Suspended",
+            "Reconciliation is paused by spec.suspend",
+            generation,
+        );
+        conditions::set_condition(
+            &mut conditions,
+            READY,
+            false,
+            "Suspended",
+            "Reconciliation is paused by spec.suspend",
+            generation,
+        );
+        patch_status(
+            doc,
+            &ctx,
+            json!({ "conditions": conditions, "observedGeneration": generation }),
+        )
+        .await?;
+
+        // Policy and target watches keep triggering suspended `Labeler`s, only the pause is reported
+        if !suspended {
+            publish_event(
+                &ctx.recorder,
+                EventType::Normal,
+                "Suspended",
+                "Reconcile",
+                Some("Reconciliation is paused by spec.suspend".to_string()),
+                &oref,
+            )
+            .await;
+        }
+
+        return Ok(Action::await_change());
+    }
+
+    if let Err(e) = doc.spec().validate() {
+        warn!(error = %e, "labeler spec is invalid, skipping reconciliation");
+
+        conditions::set_invalid(&mut conditions, &e.to_string(), generation);
+        patch_status(
+            doc,
+            &ctx,
+            json!({ "conditions": conditions, "observedGeneration": generation }),
+        )
+        .await?;
+
+        publish_event(
+            &ctx.recorder,
+            EventType::Warning,
+            "InvalidSpec",
+            "Validate",
+            Some(e.to_string()),
+            &oref,
+        )
+        .await;
+
+        return Ok(Action::await_change());
+    }
+
+    let interval = reconcile_interval(doc, &ctx.config)?;
+    let sources = policy::load_sources(doc, &ctx.client, &ctx.config).await?;
+
     info!("starting reconciliation");
 
-    let (api, ar) = discover_target_resources(&doc, &ctx.client).await?;
-    let resources = api.list(&ListParams::default()).await?;
+    if previous.observed_generation != generation || previous.policy_revision != sources.revision {
+        conditions::set_condition(
+            &mut conditions,
+            RECONCILING,
+            true,
+            "Progressing",
+            "Applying the current generation",
+            generation,
+        );
+        patch_status(
+            doc,
+            &ctx,
+            json!({ "conditions": conditions, "policyRevision": sources.revision }),
+        )
+        .await?;
+    }
 
-    let total = i32::try_from(resources.items.len())?;
-    info!(total_resources = total, "discovered target resources");
+    let targets = labeler_targets(doc)?;
 
     let mut engine = regorus::Engine::new();
-    let rego = doc.spec.rego.clone();
+    handle_rego_rule(&mut engine, doc.spec().rego.as_ref(), uid)?;
+    sources.add_to(&mut engine)?;
+
+    let stickers = Stickers::from_labeler(doc, uid);
+
+    let mut status = LabelerStatus {
+        conditions,
+        observed_generation: generation,
+        policy_revision: sources.revision,
+        ..LabelerStatus::default()
+    };
+    conditions::set_condition(
+        &mut status.conditions,
+        SUSPENDED,
+        false,
+        "Active",
+        "Labeler is reconciled",
+        generation,
+    );
+
+    for target in &targets {
+        let target_status =
+            label_target(doc, &ctx, target, &mut engine, &stickers, &mut status).await?;
+
+        status.resources_matched += target_status.resources_matched;
+        status.resources_labeled += target_status.resources_labeled;
+        status.resources_annotated += target_status.resources_annotated;
+        status.resources_pruned += target_status.resources_pruned;
+        status.resources_conflicted += target_status.resources_conflicted;
+        status.resources_skipped += target_status.resources_skipped;
+        status.resources_failed += target_status.resources_failed;
+        status.targets.push(target_status);
+    }
+
+    status.recent_changes.extend(previous.recent_changes);
+    status.recent_changes.truncate(ctx.config.status_list_limit);
+
+    conditions::set_reconciled(&mut status, doc.spec().dry_run);
+    status.last_reconcile_time = Some(Time(Utc::now()));
+    status.last_reconcile_duration = Some(format!("{}ms", started.elapsed().as_millis()));
+
+    let LabelerStatus {
+        resources_matched: total,
+        resources_labeled,
+        resources_annotated,
+        resources_pruned,
+        resources_conflicted,
+        resources_skipped,
+        resources_failed,
+        ..
+    } = status;
+
+    flush_state_to_api(doc, &ctx, status).await?;
+
+    let (reason, summary) = if doc.spec().dry_run {
+        (
+            "DryRunComplete",
+            format!(
+                "Dry run: would label {resources_labeled} and annotate {resources_annotated} of {total} resources ({resources_pruned} pruned, {resources_conflicted} conflicted, {resources_failed} failed, {resources_skipped} skipped)"
+            ),
+        )
+    } else {
+        (
+            "ReconciliationComplete",
+            format!(
+                "Labeled {resources_labeled} and annotated {resources_annotated} of {total} resources ({resources_pruned} pruned, {resources_conflicted} conflicted, {resources_failed} failed, {resources_skipped} skipped)"
+            ),
+        )
+    };
+
+    publish_event(
+        &ctx.recorder,
+        EventType::Normal,
+        reason,
+        "Reconcile",
+        Some(summary),
+        &oref,
+    )
+    .await;
+
+    info!(
+        resources_matched = total,
+        resources_labeled = resources_labeled,
+        resources_annotated = resources_annotated,
+        resources_pruned = resources_pruned,
+        resources_conflicted = resources_conflicted,
+        resources_skipped = resources_skipped,
+        resources_failed = resources_failed,
+        requeue_after_secs = interval.as_secs(),
+        "reconciliation completed successfully"
+    );
+
+    Ok(Action::requeue(interval))
+}
+
+/// Labels every resource of a single target, the changed, conflicting and failed resources and the
+/// planned changes of a dry-run `Labeler` are appended to `status` until the status list limit is
+/// reached.
+///
+/// # Errors
+///
+/// This function will return an error if any of the k8s api calls fail, see `crate::Error` for
+/// explicit error details.
+#[instrument(skip_all, fields(
+    resource_api = %target.resource_api,
+    resource_kind = %target.resource_kind,
+))]
+async fn label_target<K: LabelerResource>(
+    doc: &K,
+    ctx: &Context,
+    target: &TargetResource,
+    engine: &mut Engine,
+    stickers: &Stickers,
+    labeler_status: &mut LabelerStatus,
+) -> Result<TargetStatus> {
+    let name = doc.name_any();
+    let dry_run = doc.spec().dry_run;
+    let oref = doc.object_ref(&());
+    let ownership_key = ownership_annotation(&doc.uid().unwrap_or_default());
+    let rego = doc.spec().rego.as_ref();
+    let limit = ctx.config.status_list_limit;
 
-    handle_rego_rule(&mut engine, rego.as_ref(), uid)?;
+    let (ar, resources) = select_target_resources(doc, ctx, target).await?;
 
-    let mut resources_labeled = 0;
-    let mut resources_skipped = 0;
+    let mut status = TargetStatus {
+        resource_api: target.resource_api.clone(),
+        resource_kind: target.resource_kind.clone(),
+        resources_matched: i32::try_from(resources.len())?,
+        ..TargetStatus::default()
+    };
+    info!(
+        total_resources = status.resources_matched,
+        "discovered target resources"
+    );
 
     for resource in &resources {
         let target = resource.name_any();
@@ -166,131 +764,585 @@ async fn reconcile(doc: Arc<Labeler>, ctx: Arc<Context>) -> Result<Action> {
             None => "resource".to_string(),
         };
 
-        let can_patch = match &rego {
-            Some(r) => {
-                engine.set_input_json(&serde_json::to_string(&resource)?)?;
-                engine.eval_bool_query(r.query.clone(), false)?
-            }
-            None => true,
+        let policy = evaluate_rule(engine, rego, resource)?;
+        let matches = policy.is_some();
+        let resource_stickers = match policy {
+            Some(policy) => stickers.with_policy(policy),
+            None => Ok(stickers.clone()),
         };
 
-        let patch = patch_resource_labels(&doc, &resource.metadata);
+        let prune = doc
+            .spec()
+            .prune_on_mismatch
+            .then_some(ownership_key.as_str());
+
+        let change = match resource_stickers.and_then(|resource_stickers| {
+            plan_change(
+                &resource_stickers,
+                resource,
+                matches,
+                prune,
+                doc.spec().conflict_policy,
+            )
+        }) {
+            Ok(change) => change,
+            Err(e) => {
+                let Some((reason, message)) = skipped_resource(&e) else {
+                    return Err(e);
+                };
+
+                warn!(
+                    target_resource = %target,
+                    target_namespace = target_namespace.as_deref(),
+                    target_kind = %kind,
+                    error = %e,
+                    "skipping resource, unable to compute its labels"
+                );
 
-        if can_patch {
-            if let Some(patch_value) = patch {
                 publish_event(
                     &ctx.recorder,
-                    EventType::Normal,
-                    "AdjustingLabels",
+                    EventType::Warning,
+                    reason,
                     "Labeling",
-                    Some(format!("Labeling {kind}: {target} with rule: {name}")),
+                    Some(format!("{message} for {kind}: {target} with rule: {name}")),
                     &oref,
                 )
                 .await;
 
-                let patch_api = if let Some(ns) = &target_namespace {
-                    Api::namespaced_with(ctx.client.clone(), ns, &ar)
-                } else {
-                    api.clone()
-                };
+                record_activity(
+                    &mut labeler_status.failed_resources,
+                    limit,
+                    target_ref(&ar, resource),
+                    ResourceAction::Failed,
+                    message,
+                );
+                status.resources_failed += 1;
+                continue;
+            }
+        };
 
-                patch_api
-                    .patch(
-                        &target,
-                        &PatchParams::default(),
-                        #[allow(clippy::unwrap_used)]
-                        &Patch::Merge(patch_value),
+        // Keys that already hold the desired value are only taken over by the field manager
+        let manager = &stickers.manager;
+        if !dry_run
+            && let Some(Change::Adopt(patch_value)) = &change
+            && !apply_target(&ctx.client, &ar, resource, patch_value, manager).await?
+        {
+            continue;
+        }
+
+        match change {
+            Some(Change::Label(patch_value)) => {
+                let change = planned_change(&ar, resource, &patch_value);
+
+                if dry_run {
+                    count_change(&mut status, &change);
+                    record_planned_change(labeler_status, limit, change);
+                } else {
+                    publish_event(
+                        &ctx.recorder,
+                        EventType::Normal,
+                        "AdjustingLabels",
+                        "Labeling",
+                        Some(format!("Labeling {kind}: {target} with rule: {name}")),
+                        &oref,
                     )
-                    .await?;
+                    .await;
+
+                    if !apply_target(&ctx.client, &ar, resource, &patch_value, manager).await? {
+                        continue;
+                    }
+
+                    debug!(
+                        target_resource = %target,
+                        "successfully patched resource"
+                    );
+
+                    count_change(&mut status, &change);
+                    let keys = change.labels.keys().chain(change.annotations.keys());
+                    record_activity(
+                        &mut labeler_status.recent_changes,
+                        limit,
+                        change.resource,
+                        ResourceAction::Labeled,
+                        format!("Set {} with rule: {name}", join_keys(keys)),
+                    );
+                }
+            }
+            Some(Change::Prune(patch_value)) => {
+                let change = planned_change(&ar, resource, &patch_value);
 
-                debug!(
+                if dry_run {
+                    record_planned_change(labeler_status, limit, change);
+                } else {
+                    publish_event(
+                        &ctx.recorder,
+                        EventType::Normal,
+                        "PruningLabels",
+                        "Pruning",
+                        Some(format!("Pruning {kind}: {target} with rule: {name}")),
+                        &oref,
+                    )
+                    .await;
+
+                    if !apply_target(&ctx.client, &ar, resource, &patch_value, manager).await? {
+                        continue;
+                    }
+
+                    debug!(
+                        target_resource = %target,
+                        "successfully pruned resource"
+                    );
+
+                    let keys = change
+                        .removed_labels
+                        .iter()
+                        .chain(&change.removed_annotations);
+                    record_activity(
+                        &mut labeler_status.recent_changes,
+                        limit,
+                        change.resource,
+                        ResourceAction::Pruned,
+                        format!("Removed {} with rule: {name}", join_keys(keys)),
+                    );
+                }
+
+                status.resources_pruned += 1;
+            }
+            Some(Change::Conflict(keys)) => {
+                warn!(
                     target_resource = %target,
-                    "successfully patched resource"
+                    target_namespace = target_namespace.as_deref(),
+                    target_kind = %kind,
+                    conflicting_keys = ?keys,
+                    "skipping resource, existing values conflict with the labeler"
                 );
 
-                resources_labeled += 1;
-            } else {
+                publish_event(
+                    &ctx.recorder,
+                    EventType::Warning,
+                    "LabelConflict",
+                    "Labeling",
+                    Some(format!(
+                        "Conflicting values on {kind}: {target} for {} with rule: {name}",
+                        keys.join(", ")
+                    )),
+                    &oref,
+                )
+                .await;
+
+                if labeler_status.conflicts.len() < limit {
+                    labeler_status.conflicts.push(LabelConflict {
+                        resource: target_ref(&ar, resource),
+                        keys,
+                    });
+                }
+                status.resources_conflicted += 1;
+            }
+            Some(Change::Adopt(_)) | None => {
                 debug!(
                     target_resource = %target,
                     target_namespace = target_namespace.as_deref(),
                     target_kind = %kind,
-                    reason = "labels_already_applied",
+                    reason = if matches {
+                        "labels_already_applied"
+                    } else {
+                        "rego_policy_rejected"
+                    },
                     "skipping resource"
                 );
-                resources_skipped += 1;
+                status.resources_skipped += 1;
             }
-        } else {
-            debug!(
-                target_resource = %target,
-                target_namespace = target_namespace.as_deref(),
-                target_kind = %kind,
-                reason = "rego_policy_rejected",
-                "skipping resource"
-            );
-            resources_skipped += 1;
         }
     }
 
+    Ok(status)
+}
+
+/// Returns true if the `Labeler` labels a single object outside of the reconciliation, on admission
+/// or when the target watchers see it change, checked before its policy is compiled. The namespace
+/// selector is matched against the namespaces of the reflector store, so no API calls are made.
+/// Suspended and dry-run `Labeler`s and `Labeler`s with a field selector (only evaluated by the
+/// API server) are left to the reconciliation. `Labeler`s being deleted don't label anything, the
+/// requests removing their keys on cleanup pass through the webhook as well.
+///
+/// # Errors
+///
+/// This function will return an error if the label or namespace selector is invalid.
+pub(crate) fn labels_object<K: LabelerResource>(
+    labeler: &K,
+    kind: &GroupVersionKind,
+    meta: &ObjectMeta,
+    namespaces: &Store<PartialObjectMeta<Namespace>>,
+    config: &Config,
+) -> Result<bool> {
+    let spec = labeler.spec();
+    if labeler.uid().is_none()
+        || labeler.meta().deletion_timestamp.is_some()
+        || spec.suspend
+        || spec.dry_run
+        || spec.field_selector.is_some()
+        || spec.validate().is_err()
     {
-        let mut state = ctx.state.write().await;
-        state.resources_matched = total;
-        state.resources_skipped = resources_skipped;
-        state.resources_labeled = resources_labeled;
+        return Ok(false);
     }
 
-    flush_state_to_api(&doc, &ctx).await?;
+    if !selects_object(spec, kind, meta)? {
+        return Ok(false);
+    }
 
-    publish_event(
-        &ctx.recorder,
-        EventType::Normal,
-        "ReconciliationComplete",
-        "Reconcile",
-        Some(format!(
-            "Labeled {resources_labeled} of {total} resources ({resources_skipped} skipped)"
-        )),
-        &oref,
-    )
-    .await;
+    let own = labeler.namespace();
+    let requested = match &meta.namespace {
+        Some(_) => cached_target_scope(labeler, namespaces)?,
+        None => TargetScope::Cluster,
+    };
 
-    {
-        let mut diag = ctx.diagnostics.write().await;
-        diag.last_event = Utc::now();
-    }
+    // The reconciliation reports `Labeler`s reaching namespaces they are not allowed to
+    let Ok(scope) = authorize_target_scope(requested, own.as_deref(), config) else {
+        return Ok(false);
+    };
 
-    info!(
-        resources_matched = total,
-        resources_labeled = resources_labeled,
-        resources_skipped = resources_skipped,
-        requeue_after_secs = 300,
-        "reconciliation completed successfully"
-    );
+    Ok(match (scope, &meta.namespace) {
+        (TargetScope::Namespaces(namespaces), Some(ns)) => namespaces.contains(ns),
+        _ => true,
+    })
+}
 
-    Ok(Action::requeue(Duration::from_mins(5)))
+/// Compiles the inline policy of the `Labeler` with its loaded modules and data documents, the
+/// mutating webhook caches the engine until the `Labeler` changes and the target watchers compile
+/// it once for every batch of changed objects.
+///
+/// # Errors
+///
+/// This function will return an error if the policy can't be loaded or compiled.
+pub(crate) async fn admission_engine<K: LabelerResource>(
+    labeler: &K,
+    client: &Client,
+    config: &Config,
+) -> Result<Engine> {
+    let uid = labeler.uid().unwrap_or_default();
+    let sources = policy::load_sources(labeler, client, config).await?;
+    let mut engine = Engine::new();
+    handle_rego_rule(&mut engine, labeler.spec().rego.as_ref(), &uid)?;
+    sources.add_to(&mut engine)?;
+
+    Ok(engine)
 }
 
-/// Handles any error thrown by the reconcile function by reproting it to tracing and publishing a
-/// failed event to the k8s events api, will requeue the reconcile in 1 minute.
-#[instrument(skip(object, err, ctx), fields(
-    labeler_name = %object.name_any(),
-    labeler_namespace = object.namespace().as_deref(),
-    error_type = ?err,
-))]
-#[allow(clippy::needless_pass_by_value)]
-fn error_policy(object: Arc<Labeler>, err: &Error, ctx: Arc<Context>) -> Action {
-    let err_msg = err.to_string();
+/// Returns the event reason and the failure message of errors that only skip a single target
+/// resource instead of failing the whole reconciliation.
+fn skipped_resource(error: &Error) -> Option<(&'static str, String)> {
+    match error {
+        Error::TemplateError { key, source } => Some((
+            "TemplateRenderFailed",
+            format!("Unable to render {key}: {source}"),
+        )),
+        Error::InvalidPolicyLabel { key, reason } => Some((
+            "InvalidPolicyLabel",
+            format!("Invalid label {key} returned by the policy: {reason}"),
+        )),
+        _ => None,
+    }
+}
 
-    error!(
-        error = %err_msg,
-        requeue_after_secs = 60,
+/// Computes the merge patch a `Labeler` applies to an object sent to the mutating webhook with its
+/// compiled engine, returns `None` if the object is already up to date or left to the
+/// reconciliation. The keys set by the patch end up owned by the field manager of the request, the
+/// reconciliation applies them again to take them over with the `Labeler`'s field manager.
+///
+/// # Errors
+///
+/// This function will return an error if the policy can't be evaluated.
+pub(crate) fn admission_patch<K: LabelerResource>(
+    labeler: &K,
+    object: &DynamicObject,
+    engine: &mut Engine,
+) -> Result<Option<serde_json::Value>> {
+    Ok(
+        object_change(labeler, object, engine)?.and_then(|change| match change {
+            Change::Label(patch) | Change::Adopt(patch) | Change::Prune(patch) => Some(patch),
+            Change::Conflict(_) => None,
+        }),
+    )
+}
+
+/// Applies the change of a single target object the target watchers saw change with the
+/// `Labeler`'s field manager, the other target resources aren't listed.
+///
+/// # Errors
+///
+/// This function will return an error if the policy can't be evaluated or the apply request fails.
+pub(crate) async fn label_object<K: LabelerResource>(
+    labeler: &K,
+    ctx: &Context,
+    ar: &ApiResource,
+    object: &DynamicObject,
+    engine: &mut Engine,
+) -> Result<()> {
+    let name = labeler.name_any();
+    let target = object.name_any();
+    let kind = &ar.kind;
+
+    let (patch, event) = match object_change(labeler, object, engine)? {
+        Some(Change::Label(patch)) => (patch, Some(("AdjustingLabels", "Labeling"))),
+        Some(Change::Prune(patch)) => (patch, Some(("PruningLabels", "Pruning"))),
+        // Keys that already hold the desired value are only taken over by the field manager
+        Some(Change::Adopt(patch)) => (patch, None),
+        Some(Change::Conflict(_)) | None => return Ok(()),
+    };
+
+    if let Some((reason, action)) = event {
+        publish_event(
+            &ctx.recorder,
+            EventType::Normal,
+            reason,
+            action,
+            Some(format!("{action} {kind}: {target} with rule: {name}")),
+            &labeler.object_ref(&()),
+        )
+        .await;
+    }
+
+    let manager = field_manager(labeler, &labeler.uid().unwrap_or_default());
+    if apply_target(&ctx.client, ar, object, &patch, &manager).await? {
+        debug!(
+            target_resource = %target,
+            target_namespace = object.namespace().as_deref(),
+            target_kind = %kind,
+            "successfully labeled changed resource"
+        );
+    }
+
+    Ok(())
+}
+
+/// Plans the change of a single object outside of the reconciliation with the `Labeler`'s compiled
+/// engine, returns `None` if the object is already up to date. Conflicting objects, objects whose
+/// templates can't be rendered yet and objects whose policy returns invalid labels are left to the
+/// reconciliation, which reports them.
+///
+/// # Errors
+///
+/// This function will return an error if the policy can't be evaluated.
+fn object_change<K: LabelerResource>(
+    labeler: &K,
+    object: &DynamicObject,
+    engine: &mut Engine,
+) -> Result<Option<Change>> {
+    let spec = labeler.spec();
+    let uid = labeler.uid().unwrap_or_default();
+
+    let stickers = Stickers::from_labeler(labeler, &uid);
+    let policy = evaluate_rule(engine, spec.rego.as_ref(), object)?;
+    let matches = policy.is_some();
+    let stickers = match policy {
+        Some(policy) => stickers.with_policy(policy),
+        None => Ok(stickers),
+    };
+    let prune = spec.prune_on_mismatch.then(|| ownership_annotation(&uid));
+
+    match stickers.and_then(|stickers| {
+        plan_change(
+            &stickers,
+            object,
+            matches,
+            prune.as_deref(),
+            spec.conflict_policy,
+        )
+    }) {
+        Ok(Some(Change::Conflict(_)) | None)
+        | Err(Error::TemplateError { .. } | Error::InvalidPolicyLabel { .. }) => Ok(None),
+        Ok(change) => Ok(change),
+        Err(e) => Err(e),
+    }
+}
+
+/// Returns true if the spec targets the kind of the object and its label selector (if any) matches
+/// the object's labels, the namespaces are left to the caller.
+///
+/// # Errors
+///
+/// This function will return an error if the label selector is invalid.
+fn selects_object(spec: &LabelerSpec, kind: &GroupVersionKind, meta: &ObjectMeta) -> Result<bool> {
+    let targeted = spec.targets().iter().any(|target| {
+        target.resource_kind == kind.kind
+            && target
+                .resource_api
+                .parse::<GroupVersion>()
+                .is_ok_and(|gv| gv.group == kind.group)
+    });
+    if !targeted {
+        return Ok(false);
+    }
+
+    let Some(selector) = &spec.selector else {
+        return Ok(true);
+    };
+    let labels = meta.labels.clone().unwrap_or_default();
+
+    Ok(Selector::try_from(selector.clone())?.matches(&labels))
+}
+
+/// Discovers and lists the resources of a single target selected by the `Labeler`.
+///
+/// # Errors
+///
+/// This function will return an error if any of the k8s api calls fail.
+async fn select_target_resources<K: LabelerResource>(
+    doc: &K,
+    ctx: &Context,
+    target: &TargetResource,
+) -> Result<(ApiResource, Vec<DynamicObject>)> {
+    let (ar, caps) = discover_target_resources(target, &ctx.client).await?;
+    let scope = resolve_target_scope(doc, &caps, &ctx.client, &ctx.config).await?;
+    let lp = target_list_params(doc)?;
+    let resources = list_target_resources(&ctx.client, &ar, &scope, &lp).await?;
+
+    Ok((ar, resources))
+}
+
+/// Removes every label and annotation owned by the `Labeler` from its target resources, runs when
+/// the `Labeler` is being deleted.
+/// Only keys owned by the `Labeler`'s field manager are removed, keys taken over by others are kept.
+/// Runs for dry-run `Labeler`s as well, they may have labeled the targets before dry-run was
+/// enabled. Target kinds the cluster doesn't serve anymore have nothing left to clean up.
+///
+/// # Errors
+///
+/// This function will return an error if any of the k8s api calls fail, the finalizer will stay on
+/// the `Labeler` until the cleanup succeeds.
+#[allow(clippy::needless_pass_by_value)]
+async fn cleanup_labels<K: LabelerResource>(doc: Arc<K>, ctx: Arc<Context>) -> Result<Action> {
+    let doc = doc.as_ref();
+    let name = doc.name_any();
+    let oref = doc.object_ref(&());
+    let uid = oref
+        .uid
+        .as_ref()
+        .ok_or_else(|| "Unable to find objectId".to_string())?;
+
+    info!("starting cleanup");
+
+    ctx.diagnostics.write().await.labelers.remove(uid);
+
+    let stickers = Stickers::from_labeler(doc, uid);
+
+    let mut resources_cleaned = 0;
+
+    for target in doc.spec().targets() {
+        resources_cleaned += cleanup_target(&ctx, &target, &stickers).await?;
+    }
+
+    publish_event(
+        &ctx.recorder,
+        EventType::Normal,
+        "CleanupComplete",
+        "Cleanup",
+        Some(format!(
+            "Removed labels of {name} from {resources_cleaned} resources"
+        )),
+        &oref,
+    )
+    .await;
+
+    info!(
+        resources_cleaned = resources_cleaned,
+        "cleanup completed successfully"
+    );
+
+    Ok(Action::await_change())
+}
+
+/// Removes the `Labeler`'s stickers from every resource of a single target, returns the number of
+/// cleaned up resources. The resources are picked by the keys the `Labeler`'s field manager owns
+/// in every namespace rather than the current selectors and target namespaces, which may have
+/// changed since the keys were applied. A kind that isn't served anymore is skipped with a warning.
+///
+/// # Errors
+///
+/// This function will return an error if any of the k8s api calls fail.
+#[instrument(skip_all, fields(
+    resource_api = %target.resource_api,
+    resource_kind = %target.resource_kind,
+))]
+async fn cleanup_target(
+    ctx: &Context,
+    target: &TargetResource,
+    stickers: &Stickers,
+) -> Result<usize> {
+    let (ar, _) = match discover_target_resources(target, &ctx.client).await {
+        Ok(discovered) => discovered,
+        Err(e) if is_not_found(&e) => {
+            warn!(error = %e, "target kind is not served, nothing to clean up");
+            return Ok(0);
+        }
+        Err(e) => return Err(e),
+    };
+    let api: Api<DynamicObject> = Api::all_with(ctx.client.clone(), &ar);
+    let resources = match api.list_metadata(&ListParams::default()).await {
+        Ok(list) => list
+            .items
+            .into_iter()
+            .map(|meta| {
+                let mut resource = DynamicObject::new(&meta.name_any(), &ar);
+                resource.metadata = meta.metadata;
+                resource
+            })
+            .collect::<Vec<_>>(),
+        Err(e) => match Error::from(e) {
+            e if is_not_found(&e) => {
+                warn!(error = %e, "target kind is not served, nothing to clean up");
+                return Ok(0);
+            }
+            e => return Err(e),
+        },
+    };
+    let mut resources_cleaned = 0;
+
+    for resource in &resources {
+        let Some(patch_value) = unpatch_resource_labels(stickers, &resource.metadata) else {
+            continue;
+        };
+
+        if !apply_target(&ctx.client, &ar, resource, &patch_value, &stickers.manager).await? {
+            continue;
+        }
+
+        debug!(
+            target_resource = %resource.name_any(),
+            "successfully removed labels from resource"
+        );
+
+        resources_cleaned += 1;
+    }
+
+    Ok(resources_cleaned)
+}
+
+/// Handles any error thrown by the reconcile function by reproting it to tracing and publishing a
+/// failed event to the k8s events api, will requeue the reconcile after the operator's error
+/// interval.
+#[instrument(skip(object, err, ctx), fields(
+    labeler_name = %object.name_any(),
+    labeler_namespace = object.namespace().as_deref(),
+    error_type = ?err,
+))]
+#[allow(clippy::needless_pass_by_value)]
+fn error_policy<K: LabelerResource>(object: Arc<K>, err: &Error, ctx: Arc<Context>) -> Action {
+    let err_msg = err.to_string();
+    let interval = ctx.config.jittered(ctx.config.error_interval);
+
+    error!(
+        error = %err_msg,
+        requeue_after_secs = interval.as_secs(),
         "reconciliation failed, scheduling retry"
     );
 
-    let ctx_clone = ctx.clone();
     let oref = object.object_ref(&());
+    let policy_error = is_policy_error(err);
 
     tokio::spawn(async move {
         publish_event(
-            &ctx_clone.recorder,
+            &ctx.recorder,
             EventType::Warning,
             "ReconciliationFailed",
             "Reconcile",
@@ -298,185 +1350,1995 @@ fn error_policy(object: Arc<Labeler>, err: &Error, ctx: Arc<Context>) -> Action
             &oref,
         )
         .await;
+
+        let generation = object.meta().generation;
+        let mut conditions = object
+            .status()
+            .map(|s| s.conditions.clone())
+            .unwrap_or_default();
+        conditions::set_failed(&mut conditions, &err_msg, policy_error, generation);
+
+        // The generation isn't observed until it's applied, so the retry still reports it as
+        // reconciling
+        let status = json!({
+            "conditions": conditions,
+            "lastReconcileTime": Time(Utc::now()),
+        });
+        if let Err(e) = patch_status(object.as_ref(), &ctx, status).await {
+            warn!(error = %e, "unable to report the failure in the status");
+        }
     });
 
-    Action::requeue(Duration::from_mins(1))
+    Action::requeue(interval)
+}
+
+/// Returns whether the error is caused by the rego policy, unwrapping finalizer errors.
+fn is_policy_error(err: &Error) -> bool {
+    use kube::runtime::finalizer::Error as FinalizerError;
+
+    match err {
+        Error::PolicyError(_) => true,
+        Error::FinalizerError(e) => match e.as_ref() {
+            FinalizerError::ApplyFailed(e) | FinalizerError::CleanupFailed(e) => is_policy_error(e),
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// Returns whether the error means the target kind or resource doesn't exist (anymore).
+fn is_not_found(err: &Error) -> bool {
+    match err {
+        Error::KindNotServed { .. } => true,
+        Error::KubeError(kube::Error::Api(response)) => response.code == 404,
+        _ => false,
+    }
 }
 
-/// Flushes the in-memory state from `Context` to `LabelerStatus` in the k8s api.
+/// Flushes the status computed by a reconciliation to the k8s api and records it in the
+/// diagnostics under the `Labeler`'s uid.
 ///
 /// # Errors
 ///
 /// This function will return an error if it's unable to obtain the resource's namespace or the
 /// object unique name or if the patch or encode fails.
-#[instrument(skip(doc, ctx), fields(
-    labeler_name = doc.metadata.name.as_deref(),
-    labeler_namespace = doc.metadata.namespace.as_deref(),
+#[instrument(skip(doc, ctx, status), fields(
+    labeler_name = doc.meta().name.as_deref(),
+    labeler_namespace = doc.meta().namespace.as_deref(),
 ))]
-async fn flush_state_to_api(doc: &Labeler, ctx: &Context) -> Result<Labeler> {
-    let status = ctx.state.read().await.clone();
-    let ns = &doc
-        .namespace()
-        .ok_or_else(|| Error::from("Unable to get source namespace".to_string()))?;
-    let api: Api<Labeler> = Api::namespaced(ctx.client.clone(), ns);
+async fn flush_state_to_api<K: LabelerResource>(
+    doc: &K,
+    ctx: &Context,
+    status: LabelerStatus,
+) -> Result<K> {
+    debug!(
+        resources_matched = status.resources_matched,
+        resources_labeled = status.resources_labeled,
+        resources_annotated = status.resources_annotated,
+        resources_pruned = status.resources_pruned,
+        resources_conflicted = status.resources_conflicted,
+        resources_skipped = status.resources_skipped,
+        resources_failed = status.resources_failed,
+        "flushing status to API server"
+    );
+
+    let result = patch_status(doc, ctx, serde_json::to_value(&status)?).await?;
+
+    if let Some(uid) = doc.uid() {
+        let mut diag = ctx.diagnostics.write().await;
+        diag.last_event = Utc::now();
+        diag.labelers.insert(
+            uid,
+            LabelerDiagnostics {
+                kind: K::kind(&()).to_string(),
+                name: doc.name_any(),
+                namespace: doc.namespace(),
+                status,
+            },
+        );
+    }
+
+    Ok(result)
+}
+
+/// Merges the given fields into the `Labeler`'s status.
+///
+/// # Errors
+///
+/// This function will return an error if the object has no name or the patch fails.
+async fn patch_status<K: LabelerResource>(
+    doc: &K,
+    ctx: &Context,
+    status: serde_json::Value,
+) -> Result<K> {
+    let api = doc.api(ctx.client.clone())?;
 
     let name = doc
-        .metadata
+        .meta()
         .name
         .as_ref()
         .ok_or_else(|| "Object has a missing name".to_string())?;
 
-    debug!(
-        resources_matched = status.resources_matched,
-        resources_labeled = status.resources_labeled,
-        resources_skipped = status.resources_skipped,
-        "flushing status to API server"
+    let status_patch = Patch::Merge(json!({ "status": status }));
+
+    let result = api
+        .patch_status(name, &PatchParams::default(), &status_patch)
+        .await?;
+
+    Ok(result)
+}
+
+/// Returns the jittered time until the next reconciliation of the `Labeler`.
+///
+/// # Errors
+///
+/// This function will return an error if `spec.interval` is not a valid duration.
+fn reconcile_interval<K: LabelerResource>(labeler: &K, config: &Config) -> Result<Duration> {
+    let interval = match &labeler.spec().interval {
+        Some(interval) => config::parse_duration(interval)
+            .ok_or_else(|| Error::from(format!("Invalid interval {interval:?}")))?,
+        None => config.default_interval,
+    };
+
+    Ok(config.jittered(interval))
+}
+
+/// Returns the targets of the `Labeler`.
+///
+/// # Errors
+///
+/// This function will return an error if the `Labeler` doesn't define any target.
+fn labeler_targets<K: LabelerResource>(labeler: &K) -> Result<Vec<TargetResource>> {
+    let targets = labeler.spec().targets();
+
+    if targets.is_empty() {
+        return Err(Error::from(
+            "Labeler must set resourceApi and resourceKind or at least one entry in targets"
+                .to_string(),
+        ));
+    }
+
+    Ok(targets)
+}
+
+/// Discovers the api resource and its capabilities for the api kind and version of a `Labeler`
+/// target.
+///
+/// # Errors
+///
+/// This function will return an error if it's unabled to pin the api group or kind.
+#[instrument(skip(target, client), fields(
+    resource_api = %target.resource_api,
+    resource_kind = %target.resource_kind,
+))]
+pub(crate) async fn discover_target_resources(
+    target: &TargetResource,
+    client: &Client,
+) -> Result<(ApiResource, ApiCapabilities)> {
+    let gv: GroupVersion = target.resource_api.parse()?;
+    let apigroup = discovery::pinned_group(client, &gv).await?;
+    let (ar, caps) = apigroup
+        .recommended_kind(&target.resource_kind)
+        .ok_or_else(|| Error::KindNotServed {
+            api: target.resource_api.clone(),
+            kind: target.resource_kind.clone(),
+        })?;
+
+    Ok((ar, caps))
+}
+
+/// Resolves the namespaces the `Labeler` targets and checks if it's allowed to reach them.
+/// Namespaced `Labeler`s only target their own namespace by default, `ClusterLabeler`s target every
+/// namespace.
+///
+/// # Errors
+///
+/// This function will return an error if the namespace selector is invalid, the namespaces can't
+/// be listed or the `Labeler` is not allowed to target resources outside of its own namespace.
+async fn resolve_target_scope<K: LabelerResource>(
+    labeler: &K,
+    caps: &ApiCapabilities,
+    client: &Client,
+    config: &Config,
+) -> Result<TargetScope> {
+    let own = labeler.namespace();
+
+    let requested = if caps.scope == Scope::Cluster {
+        TargetScope::Cluster
+    } else {
+        requested_target_scope(labeler, client, own.as_deref()).await?
+    };
+
+    authorize_target_scope(requested, own.as_deref(), config)
+}
+
+/// Collects the namespaces requested by `targetNamespaces` and `namespaceSelector`, falls back to
+/// the `Labeler`'s own namespace if neither is set or every namespace for cluster scoped kinds.
+///
+/// # Errors
+///
+/// This function will return an error if the namespace selector is invalid or the namespaces can't
+/// be listed.
+async fn requested_target_scope<K: LabelerResource>(
+    labeler: &K,
+    client: &Client,
+    own: Option<&str>,
+) -> Result<TargetScope> {
+    let spec = labeler.spec();
+
+    if spec.target_namespaces.is_empty() && spec.namespace_selector.is_none() {
+        return Ok(match own {
+            Some(own) => TargetScope::Namespaces(BTreeSet::from([own.to_string()])),
+            None => TargetScope::Cluster,
+        });
+    }
+
+    let mut namespaces: BTreeSet<String> = spec.target_namespaces.iter().cloned().collect();
+
+    if let Some(selector) = &spec.namespace_selector {
+        let selector = Selector::try_from(selector.clone())?;
+        if selector.selects_all() {
+            return Ok(TargetScope::Cluster);
+        }
+
+        let api: Api<Namespace> = Api::all(client.clone());
+        let matched = api
+            .list_metadata(&ListParams::default().labels_from(&selector))
+            .await?;
+        namespaces.extend(matched.items.iter().map(ResourceExt::name_any));
+    }
+
+    Ok(TargetScope::Namespaces(namespaces))
+}
+
+/// Collects the namespaces requested by the `Labeler` like `requested_target_scope`, but matches
+/// the namespace selector against the namespaces of the reflector store instead of listing them.
+///
+/// # Errors
+///
+/// This function will return an error if the namespace selector is invalid.
+fn cached_target_scope<K: LabelerResource>(
+    labeler: &K,
+    namespaces: &Store<PartialObjectMeta<Namespace>>,
+) -> Result<TargetScope> {
+    let spec = labeler.spec();
+
+    let Some(selector) = &spec.namespace_selector else {
+        return Ok(watched_target_scope(labeler));
+    };
+
+    let selector = Selector::try_from(selector.clone())?;
+    if selector.selects_all() {
+        return Ok(TargetScope::Cluster);
+    }
+
+    let mut selected: BTreeSet<String> = spec.target_namespaces.iter().cloned().collect();
+    selected.extend(
+        namespaces
+            .state()
+            .iter()
+            .filter(|namespace| selector.matches(namespace.labels()))
+            .map(|namespace| namespace.name_any()),
     );
 
-    let status_patch = Patch::Merge(json!({"status": serde_json::to_value(status)?}));
+    Ok(TargetScope::Namespaces(selected))
+}
+
+/// Returns the namespaces the target watchers cover for the `Labeler` without resolving its
+/// namespace selector, a selector can match any namespace so it's watched cluster-wide.
+pub(crate) fn watched_target_scope<K: LabelerResource>(labeler: &K) -> TargetScope {
+    let spec = labeler.spec();
+
+    if spec.namespace_selector.is_some() {
+        return TargetScope::Cluster;
+    }
+
+    if !spec.target_namespaces.is_empty() {
+        return TargetScope::Namespaces(spec.target_namespaces.iter().cloned().collect());
+    }
+
+    match labeler.namespace() {
+        Some(own) => TargetScope::Namespaces(BTreeSet::from([own])),
+        None => TargetScope::Cluster,
+    }
+}
+
+/// Allows scopes that stay in the `Labeler`'s own namespace, anything else is only allowed if the
+/// operator allows cluster-wide `Labeler`s in the namespace. `ClusterLabeler`s (without an own
+/// namespace) may reach any scope.
+///
+/// # Errors
+///
+/// This function will return an error if the `Labeler` is not allowed to reach the scope.
+fn authorize_target_scope(
+    scope: TargetScope,
+    own: Option<&str>,
+    config: &Config,
+) -> Result<TargetScope> {
+    let Some(own) = own else {
+        return Ok(scope);
+    };
+
+    let is_own = matches!(&scope, TargetScope::Namespaces(ns) if ns.iter().all(|n| n == own));
+
+    if is_own || config.allows_cluster_wide(own) {
+        return Ok(scope);
+    }
+
+    Err(Error::from(format!(
+        "Labelers in namespace {own} are not allowed to target resources outside of their own namespace"
+    )))
+}
+
+/// Builds the list parameters that filter the target resources server side with the `Labeler`'s
+/// label and field selectors.
+///
+/// # Errors
+///
+/// This function will return an error if the label selector is invalid.
+fn target_list_params<K: LabelerResource>(labeler: &K) -> Result<ListParams> {
+    let mut lp = ListParams::default();
+
+    if let Some(selector) = &labeler.spec().selector {
+        lp = lp.labels_from(&Selector::try_from(selector.clone())?);
+    }
+
+    if let Some(fields) = &labeler.spec().field_selector {
+        lp = lp.fields(fields);
+    }
+
+    Ok(lp)
+}
+
+/// Lists every target resource in the scope.
+///
+/// # Errors
+///
+/// This function will return an error if any of the list requests fail.
+async fn list_target_resources(
+    client: &Client,
+    ar: &ApiResource,
+    scope: &TargetScope,
+    lp: &ListParams,
+) -> Result<Vec<DynamicObject>> {
+    match scope {
+        TargetScope::Cluster => {
+            let api: Api<DynamicObject> = Api::all_with(client.clone(), ar);
+            Ok(api.list(lp).await?.items)
+        }
+        TargetScope::Namespaces(namespaces) => {
+            let mut resources = Vec::new();
+            for ns in namespaces {
+                let api: Api<DynamicObject> = Api::namespaced_with(client.clone(), ns, ar);
+                resources.extend(api.list(lp).await?.items);
+            }
+            Ok(resources)
+        }
+    }
+}
+
+/// Server-side applies the labels and annotations of a `Labeler` to a single target resource with
+/// the `Labeler`'s field manager in a single request. The API server removes the keys the manager
+/// applied before that are missing from the patch, unless other managers own them as well.
+/// The apply configuration carries the uid of the listed resource, so a resource deleted since it
+/// was listed isn't recreated, returns false in that case.
+///
+/// # Errors
+///
+/// This function will return an error if the apply request fails.
+async fn apply_target(
+    client: &Client,
+    ar: &ApiResource,
+    resource: &DynamicObject,
+    patch: &serde_json::Value,
+    manager: &str,
+) -> Result<bool> {
+    let result = target_api(client, ar, resource)
+        .patch(
+            &resource.name_any(),
+            &PatchParams::apply(manager).force(),
+            &Patch::Apply(apply_configuration(ar, resource, patch)),
+        )
+        .await;
+
+    match result {
+        Ok(_) => Ok(true),
+        Err(kube::Error::Api(response)) if is_gone(response.code) => {
+            debug!(
+                target_resource = %resource.name_any(),
+                "resource is gone, skipping"
+            );
+            Ok(false)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Returns whether the status code of an apply request means the resource was deleted since it was
+/// listed, a forced apply only conflicts if the uid precondition fails.
+fn is_gone(code: u16) -> bool {
+    code == 404 || code == 409
+}
+
+/// Returns an api client for a target resource, namespaced if the resource is namespaced.
+fn target_api(client: &Client, ar: &ApiResource, resource: &DynamicObject) -> Api<DynamicObject> {
+    match resource.namespace() {
+        Some(ns) => Api::namespaced_with(client.clone(), &ns, ar),
+        None => Api::all_with(client.clone(), ar),
+    }
+}
+
+/// Builds the apply configuration of the target resource from a patch built by
+/// `patch_resource_labels`, holding only the `Labeler`'s keys. The `null` keys are left out so the
+/// API server removes them.
+fn apply_configuration(
+    ar: &ApiResource,
+    resource: &DynamicObject,
+    patch: &serde_json::Value,
+) -> serde_json::Value {
+    let mut metadata = serde_json::Map::new();
+
+    metadata.insert("name".to_string(), json!(resource.name_any()));
+    if let Some(ns) = resource.namespace() {
+        metadata.insert("namespace".to_string(), json!(ns));
+    }
+    if let Some(uid) = resource.uid() {
+        metadata.insert("uid".to_string(), json!(uid));
+    }
+
+    for field in ["labels", "annotations"] {
+        let Some(entries) = patch["metadata"][field].as_object() else {
+            continue;
+        };
+
+        let applied: serde_json::Map<_, _> = entries
+            .iter()
+            .filter(|(_, v)| !v.is_null())
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+
+        if !applied.is_empty() {
+            metadata.insert(field.to_string(), serde_json::Value::Object(applied));
+        }
+    }
+
+    json!({
+        "apiVersion": ar.api_version,
+        "kind": ar.kind,
+        "metadata": metadata,
+    })
+}
+
+/// Decides what needs to happen with a single target resource based on the rego condition result.
+/// Resources that don't match are only touched when `prune` holds the ownership annotation key and
+/// the resource carries it, matching resources are handled according to the conflict policy.
+/// Returns `None` if the resource is already up to date.
+///
+/// # Errors
+///
+/// This function will return an error if the stickers can't be rendered for the resource.
+fn plan_change(
+    stickers: &Stickers,
+    resource: &DynamicObject,
+    matches: bool,
+    prune: Option<&str>,
+    conflict_policy: ConflictPolicy,
+) -> Result<Option<Change>> {
+    if matches {
+        let meta = &resource.metadata;
+        let rendered = match conflict_policy {
+            ConflictPolicy::Overwrite => stickers.render(resource)?,
+            ConflictPolicy::Preserve => stickers.render(resource)?.preserve_existing(meta),
+            ConflictPolicy::Fail => {
+                let rendered = stickers.render(resource)?;
+                let conflicts = rendered.conflicts(meta);
+                if !conflicts.is_empty() {
+                    return Ok(Some(Change::Conflict(conflicts)));
+                }
+                rendered
+            }
+        };
+
+        return Ok(patch_resource_labels(&rendered, meta).map(|patch| {
+            if changes_metadata(meta, &patch) {
+                Change::Label(patch)
+            } else {
+                Change::Adopt(patch)
+            }
+        }));
+    }
+
+    let Some(ownership_key) = prune else {
+        return Ok(None);
+    };
+
+    if !is_owned(&resource.metadata, ownership_key) {
+        return Ok(None);
+    }
+
+    Ok(unpatch_resource_labels(stickers, &resource.metadata).map(Change::Prune))
+}
+
+/// Counts a resource as labeled and/or annotated by the keys its change sets or removes.
+fn count_change(status: &mut TargetStatus, change: &PlannedChange) {
+    if !change.labels.is_empty() || !change.removed_labels.is_empty() {
+        status.resources_labeled += 1;
+    }
+    if !change.annotations.is_empty() || !change.removed_annotations.is_empty() {
+        status.resources_annotated += 1;
+    }
+}
+
+/// Records the change a dry-run `Labeler` would apply until the status list limit is reached.
+fn record_planned_change(status: &mut LabelerStatus, limit: usize, change: PlannedChange) {
+    if status.planned_changes.len() < limit {
+        status.planned_changes.push(change);
+    }
+}
+
+/// Appends an action taken on a target resource to a status list until the limit is reached.
+fn record_activity(
+    list: &mut Vec<ResourceActivity>,
+    limit: usize,
+    resource: TargetRef,
+    action: ResourceAction,
+    reason: String,
+) {
+    if list.len() < limit {
+        list.push(ResourceActivity {
+            resource,
+            action,
+            reason,
+            timestamp: Time(Utc::now()),
+        });
+    }
+}
+
+/// Joins label and annotation keys for human readable messages.
+fn join_keys<'a>(keys: impl Iterator<Item = &'a String>) -> String {
+    keys.map(String::as_str).collect::<Vec<_>>().join(", ")
+}
+
+/// Builds the planned change of a dry-run `Labeler` from a merge patch, only the keys that differ
+/// from the resource's current metadata are recorded.
+fn planned_change(
+    ar: &ApiResource,
+    resource: &DynamicObject,
+    patch: &serde_json::Value,
+) -> PlannedChange {
+    let meta = &resource.metadata;
+    let (labels, removed_labels) =
+        diff_string_map(meta.labels.as_ref(), &patch["metadata"]["labels"]);
+    let (annotations, removed_annotations) =
+        diff_string_map(meta.annotations.as_ref(), &patch["metadata"]["annotations"]);
+
+    PlannedChange {
+        resource: target_ref(ar, resource),
+        labels,
+        removed_labels,
+        annotations,
+        removed_annotations,
+    }
+}
+
+/// Returns true if the patch sets or removes any label or annotation of the resource, patches that
+/// only take over existing keys return false.
+fn changes_metadata(meta: &ObjectMeta, patch: &serde_json::Value) -> bool {
+    [
+        (meta.labels.as_ref(), "labels"),
+        (meta.annotations.as_ref(), "annotations"),
+    ]
+    .into_iter()
+    .any(|(current, field)| {
+        let (set, removed) = diff_string_map(current, &patch["metadata"][field]);
+        !set.is_empty() || !removed.is_empty()
+    })
+}
+
+/// Splits a merge patch object into the entries that would be set to a new value and the keys
+/// that would be removed from `current`.
+fn diff_string_map(
+    current: Option<&BTreeMap<String, String>>,
+    patch: &serde_json::Value,
+) -> (BTreeMap<String, String>, Vec<String>) {
+    let mut set = BTreeMap::new();
+    let mut removed = Vec::new();
+
+    let Some(patch) = patch.as_object() else {
+        return (set, removed);
+    };
+
+    for (key, value) in patch {
+        let current_value = current.and_then(|c| c.get(key)).map(String::as_str);
+
+        match value.as_str() {
+            Some(value) if current_value != Some(value) => {
+                set.insert(key.clone(), value.to_string());
+            }
+            None if current_value.is_some() => removed.push(key.clone()),
+            _ => {}
+        }
+    }
+
+    (set, removed)
+}
+
+/// Builds a status reference to a target resource.
+fn target_ref(ar: &ApiResource, resource: &DynamicObject) -> TargetRef {
+    TargetRef {
+        api_version: ar.api_version.clone(),
+        kind: ar.kind.clone(),
+        namespace: resource.namespace(),
+        name: resource.name_any(),
+    }
+}
+
+/// Returns true if the resource carries the given ownership annotation.
+fn is_owned(meta: &ObjectMeta, ownership_key: &str) -> bool {
+    meta.annotations
+        .as_ref()
+        .is_some_and(|a| a.contains_key(ownership_key))
+}
+
+/// Builds the ownership annotation key for the `Labeler` with the given uid.
+fn ownership_annotation(uid: &str) -> String {
+    format!("{OWNERSHIP_ANNOTATION_PREFIX}{uid}")
+}
+
+/// Builds the field manager of a `Labeler`, e.g. `stickerbomb/team-a/web` for a `Labeler` or
+/// `stickerbomb/web` for a `ClusterLabeler`. Falls back to the uid if the name doesn't fit the
+/// field manager length limit.
+fn field_manager<K: LabelerResource>(labeler: &K, uid: &str) -> String {
+    let manager = match labeler.namespace() {
+        Some(ns) => format!("{FIELD_MANAGER_PREFIX}/{ns}/{}", labeler.name_any()),
+        None => format!("{FIELD_MANAGER_PREFIX}/{}", labeler.name_any()),
+    };
+
+    if manager.len() > MAX_FIELD_MANAGER_LENGTH {
+        return format!("{FIELD_MANAGER_PREFIX}/{uid}");
+    }
+
+    manager
+}
+
+/// Collects the label and annotation keys the field manager applied to the resource according to
+/// its `managedFields`.
+fn managed_keys(meta: &ObjectMeta, manager: &str) -> (BTreeSet<String>, BTreeSet<String>) {
+    fields_keys(meta, |entry| {
+        entry.manager.as_deref() == Some(manager) && entry.operation.as_deref() == Some("Apply")
+    })
+}
+
+/// Collects the label and annotation keys the field manager of any `Labeler` applied to the
+/// resource according to its `managedFields`.
+pub(crate) fn labeler_keys(meta: &ObjectMeta) -> (BTreeSet<String>, BTreeSet<String>) {
+    let prefix = format!("{FIELD_MANAGER_PREFIX}/");
+    fields_keys(meta, |entry| {
+        entry.operation.as_deref() == Some("Apply")
+            && entry
+                .manager
+                .as_deref()
+                .is_some_and(|manager| manager.starts_with(&prefix))
+    })
+}
+
+/// Collects the label and annotation keys any other field manager owns on the resource according
+/// to its `managedFields`, the API server keeps them when the field manager stops applying them.
+fn shared_keys(meta: &ObjectMeta, manager: &str) -> (BTreeSet<String>, BTreeSet<String>) {
+    fields_keys(meta, |entry| entry.manager.as_deref() != Some(manager))
+}
+
+/// Collects the label and annotation keys of the `managedFields` entries accepted by the filter.
+fn fields_keys(
+    meta: &ObjectMeta,
+    filter: impl Fn(&ManagedFieldsEntry) -> bool,
+) -> (BTreeSet<String>, BTreeSet<String>) {
+    let mut labels = BTreeSet::new();
+    let mut annotations = BTreeSet::new();
+
+    let fields = meta
+        .managed_fields
+        .iter()
+        .flatten()
+        .filter(|entry| filter(entry))
+        .filter_map(|entry| entry.fields_v1.as_ref());
+
+    for field in fields {
+        let metadata = &field.0["f:metadata"];
+        for (name, keys) in [
+            ("f:labels", &mut labels),
+            ("f:annotations", &mut annotations),
+        ] {
+            let Some(entries) = metadata[name].as_object() else {
+                continue;
+            };
+            keys.extend(
+                entries
+                    .keys()
+                    .filter_map(|k| k.strip_prefix("f:"))
+                    .map(str::to_string),
+            );
+        }
+    }
+
+    (labels, annotations)
+}
+
+/// Diffs any `ObjectMeta` with the labels and annotations of a `Labeler` and will return every
+/// key the `Labeler` applies in a k8s api format for a server-side apply, or `None` if there are no
+/// changes. Keys the API server removes are `null`, those are the ones the `Labeler`'s field
+/// manager applied before that aren't part of the stickers anymore and no other manager owns.
+/// Keys that already hold the desired value are applied again until the field manager owns them.
+fn patch_resource_labels(stickers: &Stickers, meta: &ObjectMeta) -> Option<serde_json::Value> {
+    let (managed_labels, managed_annotations) = managed_keys(meta, &stickers.manager);
+    let (shared_labels, shared_annotations) = shared_keys(meta, &stickers.manager);
+
+    let (labels, labels_changed) = apply_string_map(
+        meta.labels.as_ref(),
+        &stickers.labels,
+        &managed_labels,
+        &shared_labels,
+    );
+    let (annotations, annotations_changed) = apply_string_map(
+        meta.annotations.as_ref(),
+        &stickers.annotations,
+        &managed_annotations,
+        &shared_annotations,
+    );
+
+    if !labels_changed && !annotations_changed {
+        return None;
+    }
+
+    let mut metadata = serde_json::Map::new();
+
+    if !labels.is_empty() {
+        metadata.insert("labels".to_string(), serde_json::Value::Object(labels));
+    }
+
+    if !annotations.is_empty() {
+        metadata.insert(
+            "annotations".to_string(),
+            serde_json::Value::Object(annotations),
+        );
+    }
+
+    Some(json!({ "metadata": metadata }))
+}
+
+/// Builds the patch that stops applying every label and annotation of the `Labeler`'s field
+/// manager, or `None` if the resource doesn't carry any of them. Only the keys no other manager
+/// owns are `null` and get removed, the rest is kept on the resource.
+fn unpatch_resource_labels(stickers: &Stickers, meta: &ObjectMeta) -> Option<serde_json::Value> {
+    let released = Stickers {
+        manager: stickers.manager.clone(),
+        ..Stickers::default()
+    };
+
+    patch_resource_labels(&released, meta)
+}
+
+/// Iterates over every `desired` key that is present in `current` with a different value.
+fn conflicting_keys<'a>(
+    current: Option<&'a BTreeMap<String, String>>,
+    desired: &'a BTreeMap<String, String>,
+) -> impl Iterator<Item = &'a String> {
+    desired
+        .iter()
+        .filter(move |(k, v)| current.and_then(|c| c.get(*k)).is_some_and(|c| c != *v))
+        .map(|(k, _)| k)
+}
+
+/// Collects the `desired` entries and nulls out the `managed` keys that are no longer desired and
+/// not `shared` with other managers, as long as they are present in `current`. The flag is set if
+/// any desired entry is missing, differs or isn't managed yet, or the manager has to give up any
+/// present key.
+fn apply_string_map(
+    current: Option<&BTreeMap<String, String>>,
+    desired: &BTreeMap<String, String>,
+    managed: &BTreeSet<String>,
+    shared: &BTreeSet<String>,
+) -> (serde_json::Map<String, serde_json::Value>, bool) {
+    let present = |k: &String| current.is_some_and(|c| c.contains_key(k));
+    let released: Vec<&String> = managed
+        .iter()
+        .filter(|k| !desired.contains_key(*k) && present(k))
+        .collect();
+
+    let mut entries: serde_json::Map<String, serde_json::Value> = desired
+        .iter()
+        .map(|(k, v)| (k.clone(), serde_json::Value::String(v.clone())))
+        .collect();
+
+    for key in released.iter().filter(|k| !shared.contains(**k)) {
+        entries.insert((*key).clone(), serde_json::Value::Null);
+    }
+
+    let changed = desired
+        .iter()
+        .any(|(k, v)| current.and_then(|c| c.get(k)) != Some(v) || !managed.contains(k))
+        || !released.is_empty();
+
+    (entries, changed)
+}
+
+/// Evaluates the rego rule against the target resource, returns `None` if the resource was rejected
+/// or the labels computed by the policy otherwise (always empty in `Condition` mode or without a
+/// rule).
+///
+/// # Errors
+///
+/// This function will return an error if the query fails or returns an unexpected type.
+fn evaluate_rule(
+    engine: &mut Engine,
+    rule: Option<&RegoRule>,
+    resource: &DynamicObject,
+) -> Result<Option<PolicyLabels>> {
+    let Some(rule) = rule else {
+        return Ok(Some(PolicyLabels::default()));
+    };
+
+    engine
+        .set_input_json(&serde_json::to_string(resource)?)
+        .map_err(policy::rego_error)?;
+
+    match rule.mode {
+        RegoMode::Condition => Ok(engine
+            .eval_bool_query(rule.query.clone(), false)
+            .map_err(policy::rego_error)?
+            .then(PolicyLabels::default)),
+        RegoMode::Labels => {
+            let results = engine
+                .eval_query(rule.query.clone(), false)
+                .map_err(policy::rego_error)?;
+            let value = results
+                .result
+                .first()
+                .and_then(|r| r.expressions.first())
+                .map(|e| &e.value);
+
+            match value {
+                None
+                | Some(regorus::Value::Undefined | regorus::Value::Null | regorus::Value::Bool(false)) => {
+                    Ok(None)
+                }
+                Some(value) => serde_json::from_value(serde_json::to_value(value)?)
+                    .map(Some)
+                    .map_err(|e| {
+                        Error::PolicyError(format!(
+                            "Rego query {} must return an object with labels and remove fields: {e}",
+                            rule.query
+                        ))
+                    }),
+            }
+        }
+    }
+}
+
+/// Adds a new rego rule to the engine if needed.
+///
+/// # Errors
+///
+/// This function will return an error if it fails to add the rego rule to the engine.
+fn handle_rego_rule(engine: &mut Engine, rule: Option<&RegoRule>, uid: &str) -> Result<()> {
+    let Some(rule) = rule.filter(|r| !r.policy.is_empty()) else {
+        return Ok(());
+    };
+
+    let path = format!("{uid}.rego");
+
+    if !engine.get_policies()?.iter().any(|r| *r.get_path() == path) {
+        engine
+            .add_policy(path, rule.policy.clone())
+            .map_err(policy::rego_error)?;
+        info!("rego policy loaded successfully");
+    }
+
+    Ok(())
+}
+
+/// Helper function to publish a Kubernetes events.
+/// Will swallow any error!
+async fn publish_event(
+    recorder: &Recorder,
+    event_type: EventType,
+    reason: impl Into<String>,
+    action: impl Into<String>,
+    note: Option<String>,
+    oref: &ObjectReference,
+) {
+    let _ = recorder
+        .publish(
+            &Event {
+                type_: event_type,
+                reason: reason.into(),
+                note,
+                action: action.into(),
+                secondary: None,
+            },
+            oref,
+        )
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use http::{Request, Response};
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::{Condition, FieldsV1, LabelSelector};
+    use kube::client::Body;
+    use kube::runtime::events::Reporter;
+    use stickerbomb_crd::v1_alpha1::ClusterLabelerSpec;
+    use tower_test::mock;
+
+    use super::*;
+
+    fn test_spec() -> LabelerSpec {
+        LabelerSpec {
+            resource_api: "v1".to_string(),
+            resource_kind: "Pod".to_string(),
+            targets: Vec::new(),
+            rego: None,
+            labels: BTreeMap::default(),
+            annotations: BTreeMap::default(),
+            prune_on_mismatch: false,
+            conflict_policy: ConflictPolicy::Overwrite,
+            interval: None,
+            dry_run: false,
+            suspend: false,
+            target_namespaces: Vec::new(),
+            namespace_selector: None,
+            selector: None,
+            field_selector: None,
+        }
+    }
+
+    #[test]
+    fn test_patch_empty_resource_labels() {
+        let om = ObjectMeta::default();
+        let labeler = Labeler {
+            metadata: ObjectMeta::default(),
+            spec: LabelerSpec {
+                resource_kind: "Pods".to_string(),
+                ..test_spec()
+            },
+            status: Some(LabelerStatus::default()),
+        };
+
+        assert_eq!(
+            patch_resource_labels(&Stickers::from_labeler(&labeler, "uid"), &om),
+            None
+        );
+    }
+
+    #[test]
+    fn test_patch_resource_labels() {
+        let mut labels = BTreeMap::new();
+        labels.insert("myLabel".to_string(), "value".to_string());
+
+        let om = ObjectMeta::default();
+        let labeler = Labeler {
+            metadata: ObjectMeta::default(),
+            spec: LabelerSpec {
+                resource_kind: "Pods".to_string(),
+                labels,
+                ..test_spec()
+            },
+            status: Some(LabelerStatus::default()),
+        };
+
+        assert_eq!(
+            patch_resource_labels(&Stickers::from_labeler(&labeler, "uid"), &om),
+            Some(json!({"metadata": {"labels": {"myLabel": "value"}}}))
+        );
+    }
+
+    #[test]
+    fn test_patch_resource_annotations() {
+        let mut labels = BTreeMap::new();
+        labels.insert("myLabel".to_string(), "value".to_string());
+        let mut annotations = BTreeMap::new();
+        annotations.insert("linkerd.io/inject".to_string(), "enabled".to_string());
+
+        let om = ObjectMeta {
+            labels: Some(labels.clone()),
+            annotations: Some(BTreeMap::from([(
+                "existing".to_string(),
+                "annotation".to_string(),
+            )])),
+            ..ObjectMeta::default()
+        };
+        let labeler = Labeler {
+            metadata: ObjectMeta::default(),
+            spec: LabelerSpec {
+                labels,
+                annotations,
+                ..test_spec()
+            },
+            status: Some(LabelerStatus::default()),
+        };
+
+        assert_eq!(
+            patch_resource_labels(&Stickers::from_labeler(&labeler, "uid"), &om),
+            Some(json!({"metadata": {
+                "labels": {"myLabel": "value"},
+                "annotations": {"linkerd.io/inject": "enabled"}
+            }}))
+        );
+    }
+
+    #[test]
+    fn test_unpatch_resource_labels() {
+        let labeler = Labeler::new(
+            "test",
+            LabelerSpec {
+                labels: BTreeMap::from([("owned".to_string(), "value".to_string())]),
+                ..test_spec()
+            },
+        );
+        let stickers = Stickers::from_labeler(&labeler, "uid");
+
+        let mut managed_fields = applied_by(
+            "stickerbomb/test",
+            &["owned", "changed", "removed", "missing", "shared"],
+            &["note"],
+        );
+        managed_fields.extend(applied_by("kubectl", &["foreign", "shared"], &[]));
+        let om = ObjectMeta {
+            labels: Some(BTreeMap::from([
+                ("owned".to_string(), "value".to_string()),
+                ("changed".to_string(), "other".to_string()),
+                ("removed".to_string(), "value".to_string()),
+                ("foreign".to_string(), "value".to_string()),
+                ("shared".to_string(), "value".to_string()),
+            ])),
+            annotations: Some(BTreeMap::from([("note".to_string(), "value".to_string())])),
+            managed_fields: Some(managed_fields),
+            ..ObjectMeta::default()
+        };
+
+        assert_eq!(
+            unpatch_resource_labels(&stickers, &om),
+            Some(json!({"metadata": {
+                "labels": {"owned": null, "changed": null, "removed": null},
+                "annotations": {"note": null}
+            }}))
+        );
+
+        // Keys owned by other managers as well are only released
+        let shared = ObjectMeta {
+            labels: Some(BTreeMap::from([(
+                "shared".to_string(),
+                "value".to_string(),
+            )])),
+            annotations: None,
+            ..om.clone()
+        };
+        assert_eq!(
+            unpatch_resource_labels(&stickers, &shared),
+            Some(json!({"metadata": {}}))
+        );
+
+        // Keys with the `Labeler`'s value that its field manager never applied are kept
+        let unmanaged = ObjectMeta {
+            managed_fields: None,
+            ..om
+        };
+        assert_eq!(unpatch_resource_labels(&stickers, &unmanaged), None);
+        assert_eq!(
+            unpatch_resource_labels(&stickers, &ObjectMeta::default()),
+            None
+        );
+    }
+
+    fn test_resource(meta: ObjectMeta) -> DynamicObject {
+        DynamicObject {
+            types: None,
+            metadata: meta,
+            data: json!({"spec": {"containers": [{"image": "quay.io/org/web:1.0"}]}}),
+        }
+    }
+
+    fn applied_by(manager: &str, labels: &[&str], annotations: &[&str]) -> Vec<ManagedFieldsEntry> {
+        let fields = |keys: &[&str]| -> serde_json::Map<String, serde_json::Value> {
+            keys.iter().map(|k| (format!("f:{k}"), json!({}))).collect()
+        };
+
+        vec![ManagedFieldsEntry {
+            manager: Some(manager.to_string()),
+            operation: Some("Apply".to_string()),
+            fields_type: Some("FieldsV1".to_string()),
+            fields_v1: Some(FieldsV1(json!({"f:metadata": {
+                "f:labels": fields(labels),
+                "f:annotations": fields(annotations)
+            }}))),
+            ..ManagedFieldsEntry::default()
+        }]
+    }
+
+    #[test]
+    fn test_plan_change_prune() {
+        let labeler = Labeler {
+            metadata: ObjectMeta {
+                name: Some("test-labeler".to_string()),
+                ..ObjectMeta::default()
+            },
+            spec: LabelerSpec {
+                labels: BTreeMap::from([("team".to_string(), "a".to_string())]),
+                prune_on_mismatch: true,
+                ..test_spec()
+            },
+            status: Some(LabelerStatus::default()),
+        };
+        let stickers = Stickers::from_labeler(&labeler, "uid");
+        let ownership_key = ownership_annotation("uid");
+
+        let labels = Some(BTreeMap::from([("team".to_string(), "a".to_string())]));
+        let foreign = test_resource(ObjectMeta {
+            labels: labels.clone(),
+            ..ObjectMeta::default()
+        });
+        let owned = test_resource(ObjectMeta {
+            labels,
+            annotations: Some(BTreeMap::from([(
+                ownership_key.clone(),
+                "test-labeler".to_string(),
+            )])),
+            managed_fields: Some(applied_by(
+                "stickerbomb/test-labeler",
+                &["team"],
+                &[&ownership_key],
+            )),
+            ..ObjectMeta::default()
+        });
+
+        assert_eq!(
+            stickers.annotations.get(&ownership_key),
+            Some(&"test-labeler".to_string())
+        );
+        assert_eq!(
+            plan_change(
+                &stickers,
+                &foreign,
+                false,
+                Some(&ownership_key),
+                ConflictPolicy::Overwrite
+            )
+            .unwrap(),
+            None
+        );
+        assert_eq!(
+            plan_change(&stickers, &owned, false, None, ConflictPolicy::Overwrite).unwrap(),
+            None
+        );
+        assert_eq!(
+            plan_change(
+                &stickers,
+                &owned,
+                false,
+                Some(&ownership_key),
+                ConflictPolicy::Overwrite
+            )
+            .unwrap(),
+            Some(Change::Prune(json!({"metadata": {
+                "labels": {"team": null},
+                "annotations": {"labeler.stickerbomb.dev/uid": null}
+            }})))
+        );
+        assert_eq!(
+            plan_change(
+                &stickers,
+                &owned,
+                true,
+                Some(&ownership_key),
+                ConflictPolicy::Overwrite
+            )
+            .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_plan_change_templates() {
+        let stickers = Stickers {
+            labels: BTreeMap::from([
+                ("static".to_string(), "value".to_string()),
+                (
+                    "registry".to_string(),
+                    "{{ spec.containers[0].image | registry }}".to_string(),
+                ),
+                (
+                    "image".to_string(),
+                    "{{ spec.containers[0].image }}".to_string(),
+                ),
+            ]),
+            annotations: BTreeMap::from([(
+                "image".to_string(),
+                "{{ spec.containers[0].image }}".to_string(),
+            )]),
+            ..Stickers::default()
+        };
+
+        assert_eq!(
+            plan_change(
+                &stickers,
+                &test_resource(ObjectMeta::default()),
+                true,
+                None,
+                ConflictPolicy::Overwrite
+            )
+            .unwrap(),
+            Some(Change::Label(json!({"metadata": {
+                "labels": {
+                    "image": "quay.io-org-web-1.0",
+                    "registry": "quay.io",
+                    "static": "value"
+                },
+                "annotations": {"image": "quay.io/org/web:1.0"}
+            }})))
+        );
+
+        let broken = Stickers {
+            labels: BTreeMap::from([("team".to_string(), "{{ metadata.namespace }}".to_string())]),
+            ..Stickers::default()
+        };
+
+        assert!(matches!(
+            plan_change(&broken, &test_resource(ObjectMeta::default()), true, None, ConflictPolicy::Overwrite),
+            Err(Error::TemplateError { key, .. }) if key == "team"
+        ));
+    }
+
+    #[test]
+    fn test_evaluate_rule_labels_mode() {
+        let mut engine = regorus::Engine::new();
+        let rule = RegoRule {
+            policy: r#"package stickerbomb
+default result := {"remove": ["image-source"]}
+result := {"labels": {"image-source": "quay"}} if {
+    startswith(input.spec.containers[0].image, "quay.io/")
+}
+skip := false"#
+                .to_string(),
+            policy_ref: None,
+            data: BTreeMap::new(),
+            data_ref: None,
+            query: "data.stickerbomb.result".to_string(),
+            mode: RegoMode::Labels,
+        };
+        handle_rego_rule(&mut engine, Some(&rule), "test").unwrap();
+
+        let resource = test_resource(ObjectMeta::default());
+        let policy = evaluate_rule(&mut engine, Some(&rule), &resource)
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            policy.labels,
+            BTreeMap::from([("image-source".to_string(), "quay".to_string())])
+        );
+
+        let other = DynamicObject {
+            data: json!({"spec": {"containers": [{"image": "nginx"}]}}),
+            ..resource
+        };
+        let policy = evaluate_rule(&mut engine, Some(&rule), &other)
+            .unwrap()
+            .unwrap();
+        assert_eq!(policy.remove, BTreeSet::from(["image-source".to_string()]));
+
+        let skip = RegoRule {
+            query: "data.stickerbomb.skip".to_string(),
+            ..rule
+        };
+        assert_eq!(
+            evaluate_rule(&mut engine, Some(&skip), &other).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_patch_resource_labels_with_policy() {
+        let stickers = Stickers {
+            labels: BTreeMap::from([
+                ("team".to_string(), "a".to_string()),
+                ("tier".to_string(), "web".to_string()),
+            ]),
+            ..Stickers::default()
+        }
+        .with_policy(PolicyLabels {
+            labels: BTreeMap::from([("team".to_string(), "b".to_string())]),
+            remove: BTreeSet::from(["tier".to_string(), "legacy".to_string()]),
+        })
+        .unwrap();
+
+        let om = ObjectMeta {
+            labels: Some(BTreeMap::from([
+                ("legacy".to_string(), "true".to_string()),
+                ("tier".to_string(), "web".to_string()),
+                ("other".to_string(), "value".to_string()),
+            ])),
+            managed_fields: Some(applied_by("", &["team", "tier"], &[])),
+            ..ObjectMeta::default()
+        };
+
+        // Only the removed keys the `Labeler` applied before are removed
+        assert_eq!(
+            patch_resource_labels(&stickers, &om),
+            Some(json!({"metadata": {"labels": {
+                "team": "b",
+                "tier": null
+            }}}))
+        );
+    }
+
+    #[test]
+    fn test_with_policy_validates_labels() {
+        let stickers = Stickers::default();
+        let policy = |key: &str, value: &str| PolicyLabels {
+            labels: BTreeMap::from([(key.to_string(), value.to_string())]),
+            ..PolicyLabels::default()
+        };
+
+        // Values are sanitized like rendered templates, templates are rendered later
+        let sanitized = stickers
+            .with_policy(policy("image", "ghcr.io/org/app:1.0"))
+            .unwrap();
+        assert_eq!(sanitized.labels["image"], "ghcr.io-org-app-1.0");
+        let templated = stickers
+            .with_policy(policy("team", "{{ metadata.name }}"))
+            .unwrap();
+        assert_eq!(templated.labels["team"], "{{ metadata.name }}");
+
+        assert!(matches!(
+            stickers.with_policy(policy("not a key", "value")),
+            Err(Error::InvalidPolicyLabel { key, .. }) if key == "not a key"
+        ));
+        assert!(matches!(
+            stickers.with_policy(policy("team", "://")),
+            Err(Error::InvalidPolicyLabel { key, .. }) if key == "team"
+        ));
+    }
+
+    #[test]
+    fn test_field_manager() {
+        let mut labeler = Labeler::new("web", test_spec());
+        labeler.metadata.namespace = Some("team-a".to_string());
+        assert_eq!(field_manager(&labeler, "uid"), "stickerbomb/team-a/web");
+
+        let cluster_labeler = ClusterLabeler::new("web", ClusterLabelerSpec(test_spec()));
+        assert_eq!(field_manager(&cluster_labeler, "uid"), "stickerbomb/web");
+
+        labeler.metadata.name = Some("a".repeat(120));
+        assert_eq!(field_manager(&labeler, "uid"), "stickerbomb/uid");
+    }
+
+    #[test]
+    fn test_patch_resource_labels_managed() {
+        let stickers = Stickers {
+            labels: BTreeMap::from([("team".to_string(), "a".to_string())]),
+            manager: "stickerbomb/team-a/web".to_string(),
+            ..Stickers::default()
+        };
+        let labels = Some(BTreeMap::from([
+            ("team".to_string(), "a".to_string()),
+            ("tier".to_string(), "web".to_string()),
+        ]));
+
+        let foreign = ObjectMeta {
+            labels: labels.clone(),
+            managed_fields: Some(applied_by("kubectl", &["team", "tier"], &[])),
+            ..ObjectMeta::default()
+        };
+        assert_eq!(
+            patch_resource_labels(&stickers, &foreign),
+            Some(json!({"metadata": {"labels": {"team": "a"}}}))
+        );
+
+        let managed = ObjectMeta {
+            labels: labels.clone(),
+            managed_fields: Some(applied_by("stickerbomb/team-a/web", &["team"], &[])),
+            ..ObjectMeta::default()
+        };
+        assert_eq!(patch_resource_labels(&stickers, &managed), None);
+
+        let dropped = ObjectMeta {
+            labels,
+            managed_fields: Some(applied_by("stickerbomb/team-a/web", &["team", "tier"], &[])),
+            ..ObjectMeta::default()
+        };
+        assert_eq!(
+            patch_resource_labels(&stickers, &dropped),
+            Some(json!({"metadata": {"labels": {"team": "a", "tier": null}}}))
+        );
+    }
+
+    #[test]
+    fn test_plan_change_adopt() {
+        let stickers = Stickers {
+            labels: BTreeMap::from([("team".to_string(), "a".to_string())]),
+            manager: "stickerbomb/team-a/web".to_string(),
+            ..Stickers::default()
+        };
+        let mut resource = test_resource(ObjectMeta {
+            labels: Some(BTreeMap::from([("team".to_string(), "a".to_string())])),
+            ..ObjectMeta::default()
+        });
+
+        assert_eq!(
+            plan_change(&stickers, &resource, true, None, ConflictPolicy::Overwrite).unwrap(),
+            Some(Change::Adopt(
+                json!({"metadata": {"labels": {"team": "a"}}})
+            ))
+        );
+
+        resource.metadata.managed_fields =
+            Some(applied_by("stickerbomb/team-a/web", &["team"], &[]));
+        assert_eq!(
+            plan_change(&stickers, &resource, true, None, ConflictPolicy::Overwrite).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_managed_keys_are_not_conflicts() {
+        let stickers = Stickers {
+            labels: BTreeMap::from([("team".to_string(), "b".to_string())]),
+            manager: "stickerbomb/team-a/web".to_string(),
+            ..Stickers::default()
+        };
+        let labels = Some(BTreeMap::from([("team".to_string(), "a".to_string())]));
+
+        let foreign = ObjectMeta {
+            labels: labels.clone(),
+            ..ObjectMeta::default()
+        };
+        assert_eq!(stickers.conflicts(&foreign), vec!["team".to_string()]);
+
+        let managed = ObjectMeta {
+            labels,
+            managed_fields: Some(applied_by("stickerbomb/team-a/web", &["team"], &[])),
+            ..ObjectMeta::default()
+        };
+        assert!(stickers.conflicts(&managed).is_empty());
+    }
+
+    #[test]
+    fn test_apply_configuration() {
+        let ar = ApiResource::from_gvk(&GroupVersionKind::gvk("apps", "v1", "Deployment"));
+        let resource: DynamicObject = serde_json::from_value(json!({
+            "apiVersion": "apps/v1",
+            "kind": "Deployment",
+            "metadata": {
+                "name": "web",
+                "namespace": "team-a",
+                "uid": "web-uid",
+                "labels": {"legacy": "true"}
+            },
+            "spec": {"replicas": 1}
+        }))
+        .unwrap();
+
+        assert_eq!(
+            apply_configuration(
+                &ar,
+                &resource,
+                &json!({"metadata": {
+                    "labels": {"team": "a", "legacy": null},
+                    "annotations": {"example.com/owner": "team-a"}
+                }})
+            ),
+            json!({
+                "apiVersion": "apps/v1",
+                "kind": "Deployment",
+                "metadata": {
+                    "name": "web",
+                    "namespace": "team-a",
+                    "uid": "web-uid",
+                    "labels": {"team": "a"},
+                    "annotations": {"example.com/owner": "team-a"}
+                }
+            })
+        );
+
+        assert_eq!(
+            apply_configuration(&ar, &resource, &json!({"metadata": {}})),
+            json!({
+                "apiVersion": "apps/v1",
+                "kind": "Deployment",
+                "metadata": {"name": "web", "namespace": "team-a", "uid": "web-uid"}
+            })
+        );
+    }
+
+    #[test]
+    fn test_authorize_target_scope() {
+        let own = TargetScope::Namespaces(BTreeSet::from(["team-a".to_string()]));
+        let other =
+            TargetScope::Namespaces(BTreeSet::from(["team-a".to_string(), "team-b".to_string()]));
+        let restricted = Config::default();
+        let allowed = Config {
+            cluster_wide_namespaces: vec!["platform".to_string()],
+            ..Config::default()
+        };
+
+        assert_eq!(
+            authorize_target_scope(own, Some("team-a"), &restricted).unwrap(),
+            TargetScope::Namespaces(BTreeSet::from(["team-a".to_string()]))
+        );
+        assert!(
+            authorize_target_scope(
+                TargetScope::Namespaces(BTreeSet::new()),
+                Some("team-a"),
+                &restricted
+            )
+            .is_ok()
+        );
+        assert!(authorize_target_scope(other.clone(), Some("team-a"), &restricted).is_err());
+        assert!(authorize_target_scope(TargetScope::Cluster, Some("team-a"), &allowed).is_err());
+        assert_eq!(
+            authorize_target_scope(TargetScope::Cluster, Some("platform"), &allowed).unwrap(),
+            TargetScope::Cluster
+        );
+        assert_eq!(
+            authorize_target_scope(other.clone(), None, &restricted).unwrap(),
+            other
+        );
+    }
+
+    #[test]
+    fn test_labels_object() {
+        use kube::runtime::watcher::Event;
+
+        let config = Config::default();
+        let (namespaces, mut writer) = reflector::store();
+        let namespace = |name: &str, team: &str| -> PartialObjectMeta<Namespace> {
+            serde_json::from_value(json!({
+                "apiVersion": "v1",
+                "kind": "Namespace",
+                "metadata": {"name": name, "labels": {"team": team}}
+            }))
+            .unwrap()
+        };
+        writer.apply_watcher_event(&Event::Apply(namespace("team-a", "a")));
+        writer.apply_watcher_event(&Event::Apply(namespace("team-b", "b")));
+
+        let mut labeler = ClusterLabeler::new("test", ClusterLabelerSpec(test_spec()));
+        labeler.metadata.uid = Some("1234".to_string());
+
+        let pod_kind = GroupVersionKind::gvk("", "v1", "Pod");
+        let pod = |namespace: &str| -> ObjectMeta {
+            serde_json::from_value(json!({
+                "name": "web",
+                "namespace": namespace,
+                "labels": {"app": "web"}
+            }))
+            .unwrap()
+        };
+        let labels = |labeler: &ClusterLabeler, kind: &GroupVersionKind, namespace: &str| {
+            labels_object(labeler, kind, &pod(namespace), &namespaces, &config).unwrap()
+        };
+
+        assert!(labels(&labeler, &pod_kind, "team-b"));
+        assert!(!labels(
+            &labeler,
+            &GroupVersionKind::gvk("apps", "v1", "Deployment"),
+            "team-a"
+        ));
+
+        labeler.spec.0.namespace_selector =
+            Some(serde_json::from_value(json!({"matchLabels": {"team": "a"}})).unwrap());
+        assert!(labels(&labeler, &pod_kind, "team-a"));
+        assert!(!labels(&labeler, &pod_kind, "team-b"));
+
+        labeler.spec.0.suspend = true;
+        assert!(!labels(&labeler, &pod_kind, "team-a"));
+
+        labeler.spec.0.suspend = false;
+        labeler.metadata.deletion_timestamp = Some(Time(Utc::now()));
+        assert!(!labels(&labeler, &pod_kind, "team-a"));
+    }
+
+    #[test]
+    fn test_admission_patch() {
+        let mut labeler = Labeler::new(
+            "test",
+            LabelerSpec {
+                labels: BTreeMap::from([("team".to_string(), "a".to_string())]),
+                ..test_spec()
+            },
+        );
+        labeler.metadata.namespace = Some("team-a".to_string());
+        labeler.metadata.uid = Some("1234".to_string());
+
+        let mut pod: DynamicObject = serde_json::from_value(json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": {"name": "web", "namespace": "team-a", "labels": {"app": "web"}}
+        }))
+        .unwrap();
+
+        assert_eq!(
+            admission_patch(&labeler, &pod, &mut Engine::new()).unwrap(),
+            Some(json!({"metadata": {"labels": {"team": "a"}}}))
+        );
+
+        pod.labels_mut().insert("team".to_string(), "a".to_string());
+        pod.metadata.managed_fields = Some(applied_by("stickerbomb/team-a/test", &["team"], &[]));
+        assert_eq!(
+            admission_patch(&labeler, &pod, &mut Engine::new()).unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_adopt_after_admission() {
+        let mut labeler = Labeler::new(
+            "test",
+            LabelerSpec {
+                labels: BTreeMap::from([("team".to_string(), "a".to_string())]),
+                ..test_spec()
+            },
+        );
+        labeler.metadata.namespace = Some("team-a".to_string());
+        labeler.metadata.uid = Some("1234".to_string());
+        let stickers = Stickers::from_labeler(&labeler, "1234");
+
+        let pod = json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": {"name": "web", "namespace": "team-a", "labels": {"app": "web"}}
+        });
+        let patch = admission_patch(
+            &labeler,
+            &serde_json::from_value(pod.clone()).unwrap(),
+            &mut Engine::new(),
+        )
+        .unwrap()
+        .unwrap();
+
+        // The API server attributes the keys set by the webhook to the creator of the pod
+        let mut admitted = pod;
+        json_patch::merge(&mut admitted, &patch);
+        admitted["metadata"]["managedFields"] = json!(applied_by("kubectl", &["app", "team"], &[]));
+        let admitted: DynamicObject = serde_json::from_value(admitted).unwrap();
+
+        // The reconciliation applies the key again to take it over with the field manager
+        assert_eq!(
+            plan_change(&stickers, &admitted, true, None, ConflictPolicy::Fail).unwrap(),
+            Some(Change::Adopt(
+                json!({"metadata": {"labels": {"team": "a"}}})
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_requested_target_scope_defaults_to_own_namespace() {
+        let (mock_service, _handle) = mock::pair::<Request<Body>, Response<Body>>();
+        let client = Client::new(mock_service, "default");
+
+        let labeler = Labeler {
+            metadata: ObjectMeta::default(),
+            spec: test_spec(),
+            status: None,
+        };
+        assert_eq!(
+            requested_target_scope(&labeler, &client, Some("team-a"))
+                .await
+                .unwrap(),
+            TargetScope::Namespaces(BTreeSet::from(["team-a".to_string()]))
+        );
+
+        let labeler = Labeler {
+            spec: LabelerSpec {
+                namespace_selector: Some(LabelSelector::default()),
+                ..test_spec()
+            },
+            ..labeler
+        };
+        assert_eq!(
+            requested_target_scope(&labeler, &client, Some("team-a"))
+                .await
+                .unwrap(),
+            TargetScope::Cluster
+        );
+
+        let cluster_labeler = ClusterLabeler::new("test", ClusterLabelerSpec(test_spec()));
+        assert_eq!(
+            requested_target_scope(&cluster_labeler, &client, None)
+                .await
+                .unwrap(),
+            TargetScope::Cluster
+        );
+    }
 
-    let result = api
-        .patch_status(name, &PatchParams::default(), &status_patch)
-        .await?;
+    #[test]
+    fn test_target_list_params() {
+        use k8s_openapi::apimachinery::pkg::apis::meta::v1::LabelSelectorRequirement;
 
-    Ok(result)
-}
+        let lp = target_list_params(&Labeler {
+            metadata: ObjectMeta::default(),
+            spec: test_spec(),
+            status: None,
+        })
+        .unwrap();
+        assert_eq!(lp.label_selector, None);
+        assert_eq!(lp.field_selector, None);
 
-/// Fetch every resource from the k8s api with the api kind and version defined in the provided `Labeler`.
-///
-/// # Errors
-///
-/// This function will return an error if it's unabled to pin the api group or kind.
-#[instrument(skip(labeler, client), fields(
-    resource_api = %labeler.spec.resource_api,
-    resource_kind = %labeler.spec.resource_kind,
-))]
-async fn discover_target_resources(
-    labeler: &Labeler,
-    client: &Client,
-) -> Result<(Api<DynamicObject>, discovery::ApiResource)> {
-    let gv: GroupVersion = labeler.spec.resource_api.parse()?;
-    let apigroup = discovery::pinned_group(client, &gv).await?;
-    let (ar, _) = apigroup
-        .recommended_kind(&labeler.spec.resource_kind)
-        .ok_or_else(|| "Unable to find API kind".to_string())?;
+        let lp = target_list_params(&Labeler {
+            metadata: ObjectMeta::default(),
+            spec: LabelerSpec {
+                selector: Some(LabelSelector {
+                    match_labels: Some(BTreeMap::from([("app".to_string(), "web".to_string())])),
+                    match_expressions: Some(vec![LabelSelectorRequirement {
+                        key: "tier".to_string(),
+                        operator: "In".to_string(),
+                        values: Some(vec!["frontend".to_string()]),
+                    }]),
+                }),
+                field_selector: Some("status.phase=Running".to_string()),
+                ..test_spec()
+            },
+            status: None,
+        })
+        .unwrap();
+        assert_eq!(
+            lp.label_selector.as_deref(),
+            Some("app=web,tier in (frontend)")
+        );
+        assert_eq!(lp.field_selector.as_deref(), Some("status.phase=Running"));
 
-    Ok((Api::all_with(client.clone(), &ar), ar))
-}
+        let invalid = target_list_params(&Labeler {
+            metadata: ObjectMeta::default(),
+            spec: LabelerSpec {
+                selector: Some(LabelSelector {
+                    match_expressions: Some(vec![LabelSelectorRequirement {
+                        key: "tier".to_string(),
+                        operator: "Near".to_string(),
+                        values: None,
+                    }]),
+                    ..LabelSelector::default()
+                }),
+                ..test_spec()
+            },
+            status: None,
+        });
+        assert!(matches!(invalid, Err(Error::SelectorError(_))));
+    }
 
-/// Diffs any `ObjectMeta` with labels defined in a `Labeler` and will return the
-/// diff in a k8s api format for a patch request or return `None` if there are no changes.
-fn patch_resource_labels(labeler: &Labeler, meta: &ObjectMeta) -> Option<serde_json::Value> {
-    let mut labels = meta.labels.clone().unwrap_or_default();
-    let needs_update = labeler
-        .spec
-        .labels
-        .iter()
-        .any(|(k, v)| labels.get(k) != Some(v));
+    #[test]
+    fn test_plan_change_conflict_policy() {
+        let stickers = Stickers {
+            labels: BTreeMap::from([
+                ("team".to_string(), "a".to_string()),
+                ("tier".to_string(), "web".to_string()),
+            ]),
+            ..Stickers::default()
+        };
+        let resource = test_resource(ObjectMeta {
+            labels: Some(BTreeMap::from([("team".to_string(), "b".to_string())])),
+            ..ObjectMeta::default()
+        });
 
-    if !needs_update {
-        return None;
+        assert_eq!(
+            plan_change(&stickers, &resource, true, None, ConflictPolicy::Overwrite).unwrap(),
+            Some(Change::Label(
+                json!({"metadata": {"labels": {"team": "a", "tier": "web"}}})
+            ))
+        );
+        assert_eq!(
+            plan_change(&stickers, &resource, true, None, ConflictPolicy::Preserve).unwrap(),
+            Some(Change::Label(
+                json!({"metadata": {"labels": {"tier": "web"}}})
+            ))
+        );
+        assert_eq!(
+            plan_change(&stickers, &resource, true, None, ConflictPolicy::Fail).unwrap(),
+            Some(Change::Conflict(vec!["team".to_string()]))
+        );
     }
 
-    labels.extend(labeler.spec.labels.clone());
+    #[test]
+    fn test_labeler_targets() {
+        let deployment = TargetResource {
+            resource_api: "apps/v1".to_string(),
+            resource_kind: "Deployment".to_string(),
+        };
+        let statefulset = TargetResource {
+            resource_api: "apps/v1".to_string(),
+            resource_kind: "StatefulSet".to_string(),
+        };
 
-    Some(json!({
-        "metadata": {
-            "labels": labels
-        }
-    }))
-}
+        let labeler = Labeler::new(
+            "test",
+            LabelerSpec {
+                resource_api: "apps/v1".to_string(),
+                resource_kind: "Deployment".to_string(),
+                targets: vec![deployment.clone(), statefulset.clone()],
+                ..test_spec()
+            },
+        );
+        assert_eq!(
+            labeler_targets(&labeler).unwrap(),
+            vec![deployment, statefulset.clone()]
+        );
 
-/// Adds a new rego rule to the engine if needed.
-///
-/// # Errors
-///
-/// This function will return an error if it fails to add the rego rule to the engine.
-fn handle_rego_rule(engine: &mut Engine, rule: Option<&RegoRule>, uid: &str) -> Result<()> {
-    let Some(rule) = rule else {
-        return Ok(());
-    };
+        let labeler = Labeler::new(
+            "test",
+            LabelerSpec {
+                resource_api: String::new(),
+                resource_kind: String::new(),
+                targets: vec![statefulset.clone()],
+                ..test_spec()
+            },
+        );
+        assert_eq!(labeler_targets(&labeler).unwrap(), vec![statefulset]);
+
+        let labeler = Labeler::new(
+            "test",
+            LabelerSpec {
+                resource_api: String::new(),
+                resource_kind: String::new(),
+                ..test_spec()
+            },
+        );
+        assert!(labeler_targets(&labeler).is_err());
+    }
 
-    let path = format!("{uid}.rego");
+    #[test]
+    fn test_reconcile_interval() {
+        let config = Config {
+            jitter: 0.0,
+            ..Config::default()
+        };
+        let labeler = |interval: Option<&str>| {
+            Labeler::new(
+                "test",
+                LabelerSpec {
+                    interval: interval.map(String::from),
+                    ..test_spec()
+                },
+            )
+        };
 
-    if !engine.get_policies()?.iter().any(|r| *r.get_path() == path) {
-        engine.add_policy(path, rule.policy.clone())?;
-        info!("rego policy loaded successfully");
+        assert_eq!(
+            reconcile_interval(&labeler(None), &config).unwrap(),
+            config.default_interval
+        );
+        assert_eq!(
+            reconcile_interval(&labeler(Some("30s")), &config).unwrap(),
+            Duration::from_secs(30)
+        );
+        assert!(reconcile_interval(&labeler(Some("soon")), &config).is_err());
     }
 
-    Ok(())
-}
+    #[test]
+    fn test_spec_changes() {
+        let mut labeler = Labeler::new("web", test_spec());
+        labeler.metadata.generation = Some(1);
+        let before = spec_changes(&labeler);
+
+        labeler.status = Some(LabelerStatus {
+            resources_matched: 3,
+            ..LabelerStatus::default()
+        });
+        assert_eq!(spec_changes(&labeler), before);
 
-/// Helper function to publish a Kubernetes events.
-/// Will swallow any error!
-async fn publish_event(
-    recorder: &Recorder,
-    event_type: EventType,
-    reason: impl Into<String>,
-    action: impl Into<String>,
-    note: Option<String>,
-    oref: &ObjectReference,
-) {
-    let _ = recorder
-        .publish(
-            &Event {
-                type_: event_type,
-                reason: reason.into(),
-                note,
-                action: action.into(),
-                secondary: None,
-            },
-            oref,
-        )
-        .await;
-}
+        labeler.metadata.generation = Some(2);
+        assert_ne!(spec_changes(&labeler), before);
 
-#[cfg(test)]
-mod tests {
-    use std::collections::BTreeMap;
+        let generation = spec_changes(&labeler);
+        labeler.metadata.finalizers = Some(vec![LABELER_FINALIZER.to_string()]);
+        assert_ne!(spec_changes(&labeler), generation);
 
-    use kube::client::Body;
+        let finalizer = spec_changes(&labeler);
+        labeler.metadata.deletion_timestamp = Some(Time(Utc::now()));
+        assert_ne!(spec_changes(&labeler), finalizer);
+    }
 
-    use super::*;
+    #[test]
+    fn test_is_policy_error() {
+        let rego = || policy::rego_error(anyhow::anyhow!("rego_parse_error"));
+
+        assert!(is_policy_error(&rego()));
+        assert!(!is_policy_error(&Error::from(anyhow::anyhow!("boom"))));
+        assert!(is_policy_error(&Error::FinalizerError(Box::new(
+            kube::runtime::finalizer::Error::ApplyFailed(rego())
+        ))));
+        assert!(!is_policy_error(&Error::from("boom".to_string())));
+    }
 
     #[test]
-    fn test_patch_empty_resource_labels() {
-        let om = ObjectMeta::default();
-        let labeler = Labeler {
-            metadata: ObjectMeta::default(),
-            spec: stickerbomb_crd::v1_alpha1::LabelerSpec {
-                resource_api: "v1".to_string(),
-                resource_kind: "Pods".to_string(),
-                rego: None,
-                labels: BTreeMap::default(),
-            },
-            status: Some(LabelerStatus::default()),
+    fn test_is_not_found() {
+        let status = |code| {
+            Error::KubeError(kube::Error::Api(kube::core::ErrorResponse {
+                status: "Failure".to_string(),
+                message: "boom".to_string(),
+                reason: "NotFound".to_string(),
+                code,
+            }))
         };
 
-        assert_eq!(patch_resource_labels(&labeler, &om), None);
+        assert!(is_not_found(&Error::KindNotServed {
+            api: "v1".to_string(),
+            kind: "Pod".to_string(),
+        }));
+        assert!(is_not_found(&status(404)));
+        assert!(!is_not_found(&status(403)));
+        assert!(!is_not_found(&Error::from("boom".to_string())));
     }
 
     #[test]
-    fn test_patch_resource_labels() {
-        let mut labels = BTreeMap::new();
-        labels.insert("myLabel".to_string(), "value".to_string());
+    fn test_planned_change() {
+        let ar = ApiResource::erase::<k8s_openapi::api::core::v1::Pod>(&());
+        let resource = test_resource(ObjectMeta {
+            name: Some("web".to_string()),
+            namespace: Some("team-a".to_string()),
+            labels: Some(BTreeMap::from([
+                ("app".to_string(), "web".to_string()),
+                ("team".to_string(), "b".to_string()),
+                ("legacy".to_string(), "true".to_string()),
+            ])),
+            ..ObjectMeta::default()
+        });
 
-        let om = ObjectMeta::default();
-        let labeler = Labeler {
-            metadata: ObjectMeta::default(),
-            spec: stickerbomb_crd::v1_alpha1::LabelerSpec {
-                resource_api: "v1".to_string(),
-                resource_kind: "Pods".to_string(),
-                rego: None,
-                labels,
-            },
-            status: Some(LabelerStatus::default()),
-        };
+        let patch = json!({"metadata": {
+            "labels": {"app": "web", "team": "a", "legacy": null},
+            "annotations": {"owner": "platform"}
+        }});
 
         assert_eq!(
-            patch_resource_labels(&labeler, &om),
-            Some(json!({"metadata": {"labels": {"myLabel": "value"}}}))
+            planned_change(&ar, &resource, &patch),
+            PlannedChange {
+                resource: TargetRef {
+                    api_version: "v1".to_string(),
+                    kind: "Pod".to_string(),
+                    namespace: Some("team-a".to_string()),
+                    name: "web".to_string(),
+                },
+                labels: BTreeMap::from([("team".to_string(), "a".to_string())]),
+                removed_labels: vec!["legacy".to_string()],
+                annotations: BTreeMap::from([("owner".to_string(), "platform".to_string())]),
+                removed_annotations: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_record_activity() {
+        let resource = TargetRef {
+            api_version: "v1".to_string(),
+            kind: "Pod".to_string(),
+            namespace: Some("default".to_string()),
+            name: "web".to_string(),
+        };
+        let mut list = Vec::new();
+
+        for _ in 0..3 {
+            record_activity(
+                &mut list,
+                2,
+                resource.clone(),
+                ResourceAction::Labeled,
+                format!(
+                    "Set {}",
+                    join_keys(["team", "env"].map(String::from).iter())
+                ),
+            );
+        }
+
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].resource, resource);
+        assert_eq!(list[0].reason, "Set team, env");
+
+        record_activity(
+            &mut list,
+            0,
+            resource,
+            ResourceAction::Failed,
+            String::new(),
         );
+        assert_eq!(list.len(), 2);
     }
 
     #[test]
@@ -490,7 +3352,11 @@ allow if {
     input.spec.resourceKind == "Pod"
 }"#
             .to_string(),
+            policy_ref: None,
+            data: BTreeMap::new(),
+            data_ref: None,
             query: "data.stickerbomb.allow".to_string(),
+            mode: RegoMode::Condition,
         };
 
         assert_eq!(handle_rego_rule(&mut engine, None, uid).unwrap(), ());
@@ -500,21 +3366,12 @@ allow if {
 
     #[tokio::test]
     async fn test_discover_target_resources_with_mock() {
-        use http::{Request, Response};
-        use tower_test::mock;
-
         let (mock_service, mut handle) = mock::pair::<Request<Body>, Response<Body>>();
         let client = Client::new(mock_service, "default");
 
-        let labeler = Labeler {
-            metadata: ObjectMeta::default(),
-            spec: stickerbomb_crd::v1_alpha1::LabelerSpec {
-                resource_api: "v1".to_string(),
-                resource_kind: "Pod".to_string(),
-                rego: None,
-                labels: BTreeMap::default(),
-            },
-            status: Some(LabelerStatus::default()),
+        let target = TargetResource {
+            resource_api: "v1".to_string(),
+            resource_kind: "Pod".to_string(),
         };
 
         tokio::spawn(async move {
@@ -542,16 +3399,531 @@ allow if {
             send.send_response(response);
         });
 
-        let result = discover_target_resources(&labeler, &client).await;
+        let result = discover_target_resources(&target, &client).await;
         assert!(result.is_ok());
     }
 
+    fn test_context(client: Client) -> Context {
+        let reporter = Reporter {
+            controller: "test-controller".into(),
+            instance: Some("test-instance".into()),
+        };
+
+        Context {
+            recorder: Recorder::new(client.clone(), reporter),
+            client,
+            diagnostics: Arc::default(),
+            config: Config::default(),
+        }
+    }
+
+    fn pod_resources() -> serde_json::Value {
+        json!({
+            "kind": "APIResourceList",
+            "apiVersion": "v1",
+            "groupVersion": "v1",
+            "resources": [{
+                "name": "pods",
+                "singularName": "pod",
+                "namespaced": true,
+                "kind": "Pod",
+                "verbs": ["get", "list", "watch", "create", "update", "patch", "delete"]
+            }]
+        })
+    }
+
+    fn json_response(status: u16, body: &serde_json::Value) -> Response<Body> {
+        Response::builder()
+            .status(status)
+            .body(Body::from(serde_json::to_vec(body).unwrap()))
+            .unwrap()
+    }
+
     #[tokio::test]
-    async fn test_publish_event_with_mock() {
-        use http::{Request, Response};
-        use kube::runtime::events::Reporter;
-        use tower_test::mock;
+    async fn test_cleanup_target_with_mock() {
+        let (mock_service, mut handle) = mock::pair::<Request<Body>, Response<Body>>();
+        let ctx = test_context(Client::new(mock_service, "default"));
+
+        let mut labeler = Labeler::new(
+            "test",
+            LabelerSpec {
+                labels: BTreeMap::from([
+                    ("team".to_string(), "a".to_string()),
+                    ("tier".to_string(), "web".to_string()),
+                ]),
+                ..test_spec()
+            },
+        );
+        labeler.metadata.namespace = Some("team-a".to_string());
+        labeler.metadata.uid = Some("1234".to_string());
+        let stickers = Stickers::from_labeler(&labeler, "1234");
+        let target = TargetResource {
+            resource_api: "v1".to_string(),
+            resource_kind: "Pod".to_string(),
+        };
+
+        let mut managed_fields = applied_by("stickerbomb/team-a/test", &["team", "tier"], &[]);
+        managed_fields.extend(applied_by("kubectl", &["tier"], &[]));
+
+        let server = tokio::spawn(async move {
+            let (request, send) = handle.next_request().await.unwrap();
+            assert_eq!(request.uri().path(), "/api/v1");
+            send.send_response(json_response(200, &pod_resources()));
+
+            // Pods are listed in every namespace, not only the targeted ones
+            let (request, send) = handle.next_request().await.unwrap();
+            assert_eq!(request.method(), http::Method::GET);
+            assert_eq!(request.uri().path(), "/api/v1/pods");
+            send.send_response(json_response(
+                200,
+                &json!({
+                    "kind": "PartialObjectMetadataList",
+                    "apiVersion": "meta.k8s.io/v1",
+                    "metadata": {},
+                    "items": [
+                        {
+                            "kind": "PartialObjectMetadata",
+                            "apiVersion": "meta.k8s.io/v1",
+                            "metadata": {
+                                "name": "web",
+                                "namespace": "team-b",
+                                "uid": "web-uid",
+                                "labels": {"team": "a", "tier": "web"},
+                                "managedFields": managed_fields
+                            }
+                        },
+                        {
+                            "kind": "PartialObjectMetadata",
+                            "apiVersion": "meta.k8s.io/v1",
+                            "metadata": {
+                                "name": "db",
+                                "namespace": "team-a",
+                                "labels": {"team": "a"}
+                            }
+                        }
+                    ]
+                }),
+            ));
+
+            // Only the pod with keys of the field manager is applied, the API server removes
+            // `team` and keeps `tier` that is owned by `kubectl` as well
+            let (request, send) = handle.next_request().await.unwrap();
+            assert_eq!(request.method(), http::Method::PATCH);
+            assert_eq!(request.uri().path(), "/api/v1/namespaces/team-b/pods/web");
+            let query = request.uri().query().unwrap();
+            assert!(query.contains("fieldManager=stickerbomb%2Fteam-a%2Ftest"));
+            assert!(query.contains("force=true"));
+            let body = request.into_body().collect_bytes().await.unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(
+                body,
+                json!({
+                    "apiVersion": "v1",
+                    "kind": "Pod",
+                    "metadata": {"name": "web", "namespace": "team-b", "uid": "web-uid"}
+                })
+            );
+            send.send_response(json_response(200, &body));
+        });
+
+        assert_eq!(cleanup_target(&ctx, &target, &stickers).await.unwrap(), 1);
+        server.await.unwrap();
+
+        // Kinds that aren't served anymore have nothing to clean up
+        let (mock_service, mut handle) = mock::pair::<Request<Body>, Response<Body>>();
+        let ctx = test_context(Client::new(mock_service, "default"));
+        let target = TargetResource {
+            resource_api: "v1".to_string(),
+            resource_kind: "Widget".to_string(),
+        };
+
+        tokio::spawn(async move {
+            let (_request, send) = handle.next_request().await.unwrap();
+            send.send_response(json_response(200, &pod_resources()));
+        });
+
+        assert_eq!(cleanup_target(&ctx, &target, &stickers).await.unwrap(), 0);
+    }
+
+    /// Requests received by the mock API server with their json body (`null` if empty)
+    type Requests = Arc<std::sync::Mutex<Vec<(http::Method, String, serde_json::Value)>>>;
+
+    /// Serves the requests of a reconciliation: discovers pods, lists the given pods, returns the
+    /// `Labeler` for status patches and accepts events, applies to pods fail as if they were
+    /// deleted. Every request is recorded.
+    fn serve_api(
+        mut handle: mock::Handle<Request<Body>, Response<Body>>,
+        labeler: serde_json::Value,
+        pods: serde_json::Value,
+    ) -> Requests {
+        let requests = Requests::default();
+        let recorded = requests.clone();
+
+        tokio::spawn(async move {
+            while let Some((request, send)) = handle.next_request().await {
+                let method = request.method().clone();
+                let path = request.uri().path().to_string();
+                let body = request.into_body().collect_bytes().await.unwrap();
+                let body = serde_json::from_slice(&body).unwrap_or_default();
+
+                let response = match (&method, path.as_str()) {
+                    (&http::Method::GET, "/api/v1") => json_response(200, &pod_resources()),
+                    (&http::Method::GET, path) if path.ends_with("/pods") => json_response(
+                        200,
+                        &json!({"kind": "List", "apiVersion": "v1", "metadata": {}, "items": pods}),
+                    ),
+                    (&http::Method::PATCH, path) if path.ends_with("/status") => {
+                        json_response(200, &labeler)
+                    }
+                    (&http::Method::POST, path) if path.ends_with("/events") => {
+                        json_response(201, &body)
+                    }
+                    _ => json_response(404, &json!({"kind": "Status", "code": 404})),
+                };
+
+                recorded.lock().unwrap().push((method, path, body));
+                send.send_response(response);
+            }
+        });
+
+        requests
+    }
+
+    #[tokio::test]
+    async fn test_label_target_counts_applied_resources() {
+        let mut labeler = Labeler::new(
+            "test",
+            LabelerSpec {
+                labels: BTreeMap::from([("team".to_string(), "a".to_string())]),
+                ..test_spec()
+            },
+        );
+        labeler.metadata.namespace = Some("team-a".to_string());
+        labeler.metadata.uid = Some("1234".to_string());
+        let target = labeler_targets(&labeler).unwrap().remove(0);
+        let stickers = Stickers::from_labeler(&labeler, "1234");
+        let pods = json!([{
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": {"name": "web", "namespace": "team-a", "uid": "web-uid"}
+        }]);
+
+        // The pod is deleted before it's labeled
+        let (mock_service, handle) = mock::pair::<Request<Body>, Response<Body>>();
+        serve_api(
+            handle,
+            serde_json::to_value(&labeler).unwrap(),
+            pods.clone(),
+        );
+        let ctx = test_context(Client::new(mock_service, "default"));
+        let mut status = LabelerStatus::default();
+        let target_status = label_target(
+            &labeler,
+            &ctx,
+            &target,
+            &mut Engine::new(),
+            &stickers,
+            &mut status,
+        )
+        .await
+        .unwrap();
+        assert_eq!(target_status.resources_matched, 1);
+        assert_eq!(target_status.resources_labeled, 0);
+        assert!(status.recent_changes.is_empty());
+
+        // Dry runs count the planned changes
+        labeler.spec.dry_run = true;
+        let (mock_service, handle) = mock::pair::<Request<Body>, Response<Body>>();
+        let requests = serve_api(handle, serde_json::to_value(&labeler).unwrap(), pods);
+        let ctx = test_context(Client::new(mock_service, "default"));
+        let mut status = LabelerStatus::default();
+        let target_status = label_target(
+            &labeler,
+            &ctx,
+            &target,
+            &mut Engine::new(),
+            &stickers,
+            &mut status,
+        )
+        .await
+        .unwrap();
+        assert_eq!(target_status.resources_labeled, 1);
+        assert_eq!(status.planned_changes.len(), 1);
+        assert!(
+            requests
+                .lock()
+                .unwrap()
+                .iter()
+                .all(|(method, _, _)| method != http::Method::PATCH)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_label_object() {
+        let mut labeler = Labeler::new(
+            "test",
+            LabelerSpec {
+                labels: BTreeMap::from([("team".to_string(), "a".to_string())]),
+                ..test_spec()
+            },
+        );
+        labeler.metadata.namespace = Some("team-a".to_string());
+        labeler.metadata.uid = Some("1234".to_string());
+        let ar = ApiResource::erase::<k8s_openapi::api::core::v1::Pod>(&());
+        let mut pod: DynamicObject = serde_json::from_value(json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": {"name": "web", "namespace": "team-a", "uid": "web-uid"}
+        }))
+        .unwrap();
+        let applies = |requests: &Requests| -> Vec<serde_json::Value> {
+            requests
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|(method, _, _)| method == http::Method::PATCH)
+                .map(|(_, _, body)| body.clone())
+                .collect()
+        };
+
+        let (mock_service, handle) = mock::pair::<Request<Body>, Response<Body>>();
+        let requests = serve_api(handle, serde_json::to_value(&labeler).unwrap(), json!([]));
+        let ctx = test_context(Client::new(mock_service, "default"));
+        label_object(&labeler, &ctx, &ar, &pod, &mut Engine::new())
+            .await
+            .unwrap();
+        assert_eq!(
+            applies(&requests),
+            vec![json!({
+                "apiVersion": "v1",
+                "kind": "Pod",
+                "metadata": {
+                    "name": "web",
+                    "namespace": "team-a",
+                    "uid": "web-uid",
+                    "labels": {"team": "a"}
+                }
+            })]
+        );
+
+        // Objects that are up to date or conflict aren't touched
+        pod.labels_mut().insert("team".to_string(), "a".to_string());
+        pod.metadata.managed_fields = Some(applied_by("stickerbomb/team-a/test", &["team"], &[]));
+        let (mock_service, handle) = mock::pair::<Request<Body>, Response<Body>>();
+        let requests = serve_api(handle, serde_json::to_value(&labeler).unwrap(), json!([]));
+        let ctx = test_context(Client::new(mock_service, "default"));
+        label_object(&labeler, &ctx, &ar, &pod, &mut Engine::new())
+            .await
+            .unwrap();
+
+        labeler.spec.conflict_policy = ConflictPolicy::Fail;
+        pod.labels_mut().insert("team".to_string(), "b".to_string());
+        pod.metadata.managed_fields = None;
+        label_object(&labeler, &ctx, &ar, &pod, &mut Engine::new())
+            .await
+            .unwrap();
+        assert!(applies(&requests).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_apply_labels_suspended() {
+        let mut labeler = Labeler::new(
+            "test",
+            LabelerSpec {
+                suspend: true,
+                ..test_spec()
+            },
+        );
+        labeler.metadata.namespace = Some("team-a".to_string());
+        labeler.metadata.uid = Some("1234".to_string());
+        labeler.metadata.generation = Some(3);
+        let mut previous = LabelerStatus::default();
+        conditions::set_condition(
+            &mut previous.conditions,
+            READY,
+            true,
+            "Reconciled",
+            "",
+            Some(2),
+        );
+        labeler.status = Some(previous);
+
+        let (mock_service, handle) = mock::pair::<Request<Body>, Response<Body>>();
+        let requests = serve_api(handle, serde_json::to_value(&labeler).unwrap(), json!([]));
+        let ctx = Arc::new(test_context(Client::new(mock_service, "default")));
+
+        let action = apply_labels(Arc::new(labeler.clone()), ctx.clone())
+            .await
+            .unwrap();
+        assert_eq!(action, Action::await_change());
+
+        // Only the conditions are patched and the event is published, no target is touched
+        let recorded = requests.lock().unwrap().clone();
+        assert_eq!(recorded.len(), 2);
+
+        let (method, path, body) = &recorded[0];
+        assert_eq!(method, http::Method::PATCH);
+        assert_eq!(
+            path,
+            "/apis/stickerbomb.dev/v1alpha1/namespaces/team-a/labelers/test/status"
+        );
+        assert_eq!(body["status"]["observedGeneration"], 3);
+        let condition = |type_: &str| {
+            body["status"]["conditions"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .find(|c| c["type"] == type_)
+                .cloned()
+                .unwrap()
+        };
+        assert_eq!(condition(SUSPENDED)["status"], "True");
+        assert_eq!(condition(READY)["status"], "False");
+        assert_eq!(condition(READY)["reason"], "Suspended");
+        assert_eq!(condition(READY)["observedGeneration"], 3);
+
+        let (method, path, body) = &recorded[1];
+        assert_eq!(method, http::Method::POST);
+        assert!(path.ends_with("/namespaces/team-a/events"));
+        assert_eq!(body["reason"], "Suspended");
+
+        // Triggers of an already suspended `Labeler` don't publish the event again
+        labeler.status.as_mut().unwrap().conditions =
+            serde_json::from_value(recorded[0].2["status"]["conditions"].clone()).unwrap();
+        requests.lock().unwrap().clear();
+        apply_labels(Arc::new(labeler), ctx).await.unwrap();
+        let recorded = requests.lock().unwrap().clone();
+        assert_eq!(recorded.len(), 1);
+        assert_eq!(recorded[0].0, http::Method::PATCH);
+    }
+
+    #[tokio::test]
+    async fn test_error_policy_keeps_observed_generation() {
+        let mut labeler = Labeler::new("test", test_spec());
+        labeler.metadata.namespace = Some("team-a".to_string());
+        labeler.metadata.uid = Some("1234".to_string());
+        labeler.metadata.generation = Some(2);
+        labeler.status = Some(LabelerStatus {
+            observed_generation: Some(1),
+            ..LabelerStatus::default()
+        });
+
+        let (mock_service, handle) = mock::pair::<Request<Body>, Response<Body>>();
+        let requests = serve_api(handle, serde_json::to_value(&labeler).unwrap(), json!([]));
+        let ctx = Arc::new(test_context(Client::new(mock_service, "default")));
+
+        error_policy(Arc::new(labeler), &Error::from("boom".to_string()), ctx);
+
+        // The failure is reported in the background
+        let status = loop {
+            let patched = requests
+                .lock()
+                .unwrap()
+                .iter()
+                .find(|(method, path, _)| {
+                    method == http::Method::PATCH && path.ends_with("/status")
+                })
+                .map(|(_, _, body)| body["status"].clone());
+            if let Some(status) = patched {
+                break status;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+
+        assert!(status.get("observedGeneration").is_none());
+        let conditions: Vec<Condition> =
+            serde_json::from_value(status["conditions"].clone()).unwrap();
+        let ready = conditions::find(&conditions, READY).unwrap();
+        assert_eq!(ready.status, "False");
+        assert_eq!(ready.message, "boom");
+    }
+
+    #[tokio::test]
+    async fn test_reconcile_builds_fresh_status() {
+        let mut labeler = Labeler::new(
+            "test",
+            LabelerSpec {
+                resource_api: "v1".to_string(),
+                resource_kind: "Pod".to_string(),
+                ..test_spec()
+            },
+        );
+        labeler.metadata.namespace = Some("team-a".to_string());
+        labeler.metadata.uid = Some("1234".to_string());
+        labeler.metadata.generation = Some(1);
+
+        // Leftovers of an earlier reconciliation that found a conflict and a failed resource
+        let pod = TargetRef {
+            api_version: "v1".to_string(),
+            kind: "Pod".to_string(),
+            namespace: Some("team-a".to_string()),
+            name: "web".to_string(),
+        };
+        let failed = ResourceActivity {
+            resource: pod.clone(),
+            action: ResourceAction::Failed,
+            reason: "Unable to render team".to_string(),
+            timestamp: Time(Utc::now()),
+        };
+        labeler.status = Some(LabelerStatus {
+            resources_matched: 2,
+            resources_conflicted: 1,
+            resources_failed: 1,
+            conflicts: vec![LabelConflict {
+                resource: pod,
+                keys: vec!["team".to_string()],
+            }],
+            failed_resources: vec![failed],
+            observed_generation: Some(1),
+            ..LabelerStatus::default()
+        });
+
+        let (mock_service, handle) = mock::pair::<Request<Body>, Response<Body>>();
+        let requests = serve_api(handle, serde_json::to_value(&labeler).unwrap(), json!([]));
+        let ctx = Arc::new(test_context(Client::new(mock_service, "default")));
+        let labeler = Arc::new(labeler);
+
+        for _ in 0..2 {
+            apply_labels(labeler.clone(), ctx.clone()).await.unwrap();
+        }
 
+        // Every reconciliation replaces the lists of the previous one
+        let patches: Vec<_> = requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(method, path, _)| method == http::Method::PATCH && path.ends_with("/status"))
+            .map(|(_, _, body)| body["status"].clone())
+            .collect();
+        assert_eq!(patches.len(), 2);
+        for status in &patches {
+            assert_eq!(status["resourcesMatched"], 0);
+            assert_eq!(status["resourcesConflicted"], 0);
+            assert_eq!(status["resourcesFailed"], 0);
+            assert_eq!(status["conflicts"], json!([]));
+            assert_eq!(status["failedResources"], json!([]));
+            assert_eq!(status["targets"].as_array().unwrap().len(), 1);
+        }
+
+        // Diagnostics hold the last status of every `Labeler` by uid
+        {
+            let diagnostics = ctx.diagnostics.read().await;
+            assert_eq!(diagnostics.labelers.len(), 1);
+            let entry = &diagnostics.labelers["1234"];
+            assert_eq!(entry.name, "test");
+            assert_eq!(entry.namespace.as_deref(), Some("team-a"));
+            assert!(entry.status.conflicts.is_empty());
+            assert!(entry.status.failed_resources.is_empty());
+            assert_eq!(entry.status.targets.len(), 1);
+        }
+
+        // The entry is dropped once the `Labeler` is cleaned up
+        cleanup_labels(labeler, ctx.clone()).await.unwrap();
+        assert!(ctx.diagnostics.read().await.labelers.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_publish_event_with_mock() {
         let (mock_service, mut handle) = mock::pair::<Request<Body>, Response<Body>>();
         let client = Client::new(mock_service, "default");
 
@@ -1,12 +1,15 @@
 // Copyright 2025 Stickerbomb Maintainers
 // SPDX-License-Identifier: Apache-2.0
 
+use std::collections::BTreeMap;
+
 use k8s_openapi::chrono::{DateTime, Utc};
 use kube::{
     Client,
     runtime::events::{Recorder, Reporter},
 };
 use serde::Serialize;
+use stickerbomb_crd::LabelerStatus;
 
 /// Diagnostics to be exposed by the web server
 #[derive(Clone, Serialize)]
@@ -14,6 +17,8 @@ pub struct Diagnostics {
     /// Last successful reconcile event
     #[serde(deserialize_with = "from_ts")]
     pub last_event: DateTime<Utc>,
+    /// Result of the last reconciliation of every `Labeler` and `ClusterLabeler`, keyed by uid
+    pub labelers: BTreeMap<String, LabelerDiagnostics>,
     /// Kuberentes status reporter
     #[serde(skip)]
     pub reporter: Reporter,
@@ -23,11 +28,27 @@ impl Default for Diagnostics {
     fn default() -> Self {
         Self {
             last_event: Utc::now(),
+            labelers: BTreeMap::new(),
             reporter: "strickerbomb".into(),
         }
     }
 }
 
+/// Last reconciliation result of a single `Labeler` or `ClusterLabeler`
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LabelerDiagnostics {
+    /// Kind of the labeler
+    pub kind: String,
+    /// Name of the labeler
+    pub name: String,
+    /// Namespace of the labeler, empty for `ClusterLabeler`s
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub namespace: Option<String>,
+    /// Status computed by the last reconciliation
+    pub status: LabelerStatus,
+}
+
 impl Diagnostics {
     /// Creates a new recoreder wrapper around self
     #[must_use]
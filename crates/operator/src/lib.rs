@@ -18,15 +18,63 @@ pub enum Error {
     #[error("Parse Error: {0}")]
     ParseError(#[from] ParseGroupVersionError),
 
+    /// Label selector parsing errors
+    #[error("Selector Error: {0}")]
+    SelectorError(#[from] ParseExpressionError),
+
     /// Int conversion errors
     #[error("Conversion Error: {0}")]
     ConversionError(#[from] TryFromIntError),
 
+    /// Errors raised while adding, running or removing the cleanup finalizer
+    #[error("Finalizer Error: {0}")]
+    FinalizerError(#[source] Box<kube::runtime::finalizer::Error<Error>>),
+
+    /// Templated label or annotation values that can't be rendered for a target resource
+    #[error("Template Error in {key}: {source}")]
+    TemplateError {
+        /// Label or annotation key of the failing template
+        key: String,
+        /// Underlying rendering error
+        #[source]
+        source: template::Error,
+    },
+
+    /// Labels computed by a rego policy that aren't valid for a target resource
+    #[error("Invalid policy label {key}: {reason}")]
+    InvalidPolicyLabel {
+        /// Label key returned by the policy
+        key: String,
+        /// Why the key or its value was rejected
+        reason: String,
+    },
+
+    /// File system errors (currently only the webhook certificates use this)
+    #[error("IO Error: {0}")]
+    IoError(#[from] std::io::Error),
+
+    /// Webhook server tls configuration errors
+    #[error("TLS Error: {0}")]
+    TlsError(#[from] rustls::Error),
+
+    /// Target kinds the API group doesn't serve
+    #[error("resourceKind {kind} is not served by {api}")]
+    KindNotServed {
+        /// `resourceApi` of the target
+        api: String,
+        /// `resourceKind` of the target
+        kind: String,
+    },
+
+    /// Rego policies that can't be loaded from the referenced objects
+    #[error("Policy Error: {0}")]
+    PolicyError(String),
+
     /// Generic string error messages
     #[error("{0}")]
     Message(String),
 
-    /// Represents any error (currently only `rego` uses this)
+    /// Represents any other error, errors of the rego engine are reported as `PolicyError`s
     #[error("Rego Error: {0}")]
     AnyhowError(#[from] anyhow::Error),
 }
@@ -40,14 +88,20 @@ impl From<String> for Error {
 /// Generic result type to be used in the controller
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
+mod conditions;
+pub mod config;
 pub mod controller;
 mod diagnostics;
+mod policy;
+mod targets;
 
 pub mod lease;
 pub mod telemetry;
+pub mod template;
+pub mod webhook;
 
 use std::num::TryFromIntError;
 
-use kube::core::gvk::ParseGroupVersionError;
+use kube::core::{ParseExpressionError, gvk::ParseGroupVersionError};
 
 pub use crate::diagnostics::*;
@@ -7,7 +7,7 @@ use actix_web::{
     App, HttpRequest, HttpResponse, HttpServer, Responder, get, middleware, web::Data,
 };
 use kube::client;
-use stickerbomb::{controller, lease::run_leader_election, telemetry};
+use stickerbomb::{config::Config, controller, lease::run_leader_election, telemetry, webhook};
 use tokio::{pin, signal, sync::watch};
 use tracing::{info, instrument};
 
@@ -27,9 +27,45 @@ async fn index(c: Data<controller::State>, _: HttpRequest) -> impl Responder {
 async fn main() -> anyhow::Result<()> {
     telemetry::init()?;
 
-    let state = controller::State::default();
+    let config = Config::from_env();
+    let state = controller::State::new(config.clone());
     let client = client::Client::try_default().await?;
 
+    let webhook_server = match &config.webhook {
+        Some(webhook_config) => {
+            let (mutation, reflectors) =
+                webhook::MutationState::new(client.clone(), config.clone());
+            tokio::spawn(reflectors);
+
+            let webhook_client = client.clone();
+            let webhook_operator_config = config.clone();
+            let server = HttpServer::new(move || {
+                App::new()
+                    .app_data(Data::new(webhook_client.clone()))
+                    .app_data(Data::new(webhook_operator_config.clone()))
+                    .app_data(Data::new(mutation.clone()))
+                    .wrap(middleware::Logger::default())
+                    .service(webhook::convert)
+                    .service(webhook::validate)
+                    .service(webhook::mutate)
+            })
+            .bind_rustls_0_23(
+                ("0.0.0.0", webhook::WEBHOOK_PORT),
+                webhook::tls_config(&webhook_config.cert_dir)?,
+            )?
+            .shutdown_timeout(5);
+
+            Some(server.run())
+        }
+        None => None,
+    };
+    let webhooks = async move {
+        match webhook_server {
+            Some(server) => server.await,
+            None => std::future::pending().await,
+        }
+    };
+
     let (leader_tx, leader_rx) = watch::channel(false);
 
     let controller = controller::run(client.clone(), state.clone(), leader_rx);
@@ -48,6 +84,7 @@ async fn main() -> anyhow::Result<()> {
     tokio::select! {
         () = &mut controller => info!("controller exited"),
         res = server.run() => res?,
+        res = webhooks => res?,
         () = run_leader_election(client.clone(), leader_tx.clone()) =>  info!("leader election exited"),
         _ = signal::ctrl_c() => {
             let _ = leader_tx.send(false);
@@ -90,5 +127,6 @@ mod tests {
 
         let body: serde_json::Value = test::read_body_json(resp).await;
         assert!(body.is_object(), "Response should be a JSON object");
+        assert_eq!(body["labelers"], serde_json::json!({}));
     }
 }
@@ -0,0 +1,657 @@
+// Copyright 2026 Stickerbomb Maintainers
+// SPDX-License-Identifier: Apache-2.0
+
+//! Rego modules and data documents of a `Labeler` loaded from other objects.
+//!
+//! `spec.rego.policyRef` points to a `ConfigMap` whose keys are loaded as modules next to the
+//! inline policy, `spec.rego.dataRef` to one whose keys are loaded as `data.<key>` next to the
+//! inline `data`. Modules importing the package of a `RegoLibrary` get the library's modules
+//! loaded as well. The resource versions of the loaded `ConfigMap`s and the generations of the
+//! loaded libraries make up the policy revision recorded in the status, so a changed `ConfigMap`
+//! or library is applied before the `Labeler`'s interval passes. Only `ConfigMap`s carrying the
+//! `POLICY_LABEL` are watched, changes of other `ConfigMap`s wait for the next reconciliation.
+
+use k8s_openapi::api::core::v1::ConfigMap;
+use kube::api::ListParams;
+use kube::runtime::reflector::{ObjectRef, Store};
+use kube::{Api, Client, Resource, ResourceExt};
+use regorus::Engine;
+use serde_json::Value;
+use stickerbomb_crd::RegoLibrary;
+use stickerbomb_crd::v1_alpha1::{ConfigMapRef, RegoRule};
+use tracing::info;
+
+use crate::config::Config;
+use crate::controller::LabelerResource;
+use crate::{Error, Result};
+
+/// Label of the `ConfigMap`s watched for changes of `policyRef` and `dataRef` modules, any value
+pub const POLICY_LABEL: &str = "stickerbomb.dev/policy";
+
+/// Rego modules and data documents loaded for a single reconciliation
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PolicySources {
+    /// Path and source of every loaded module
+    pub modules: Vec<(String, String)>,
+    /// Data documents merged into `data`
+    pub data: Vec<Value>,
+    /// Versions of the objects the modules were loaded from, `None` if there are none
+    pub revision: Option<String>,
+}
+
+impl PolicySources {
+    /// Adds every loaded module and data document to the engine.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if any of the modules can't be parsed or the data
+    /// documents have conflicting keys.
+    pub fn add_to(&self, engine: &mut Engine) -> Result<()> {
+        for (path, source) in &self.modules {
+            engine
+                .add_policy(path.clone(), source.clone())
+                .map_err(rego_error)?;
+        }
+
+        for document in &self.data {
+            engine
+                .add_data_json(&serde_json::to_string(document)?)
+                .map_err(rego_error)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Wraps an error of the rego engine (parsing, compiling or evaluating) into a policy error.
+#[allow(clippy::needless_pass_by_value)]
+pub fn rego_error(err: anyhow::Error) -> Error {
+    Error::PolicyError(err.to_string())
+}
+
+/// Loads the inline data, the rego modules and data documents referenced by the `Labeler`'s
+/// `policyRef` and `dataRef` and the modules of the imported `RegoLibrary`s.
+///
+/// # Errors
+///
+/// This function will return an error if a `ConfigMap` or one of its keys can't be found, the
+/// `Labeler` isn't allowed to read the `ConfigMap`'s namespace or an imported library has modules
+/// outside of its package.
+pub async fn load_sources<K: LabelerResource>(
+    labeler: &K,
+    client: &Client,
+    config: &Config,
+) -> Result<PolicySources> {
+    let mut sources = PolicySources::default();
+    let Some(rule) = labeler.spec().rego.as_ref() else {
+        return Ok(sources);
+    };
+
+    if !rule.data.is_empty() {
+        sources
+            .data
+            .push(Value::Object(rule.data.clone().into_iter().collect()));
+    }
+
+    let mut revisions = Vec::new();
+
+    if let Some(reference) = &rule.policy_ref {
+        let (namespace, config_map) = get_config_map(labeler, reference, client, config).await?;
+
+        let modules =
+            config_map_entries(&config_map, reference)?
+                .into_iter()
+                .map(|(key, source)| {
+                    let path = format!(
+                        "{}/{namespace}/{}/{key}",
+                        labeler.uid().unwrap_or_default(),
+                        reference.name
+                    );
+                    (path, source)
+                });
+        sources.modules.extend(modules);
+        revisions.push(revision(&namespace, &config_map));
+
+        info!(
+            config_map = %reference.name,
+            config_map_namespace = %namespace,
+            modules = sources.modules.len(),
+            "loaded rego modules from policyRef"
+        );
+    }
+
+    let imported = std::iter::once(rule.policy.as_str())
+        .chain(sources.modules.iter().map(|(_, source)| source.as_str()))
+        .flat_map(imports)
+        .map(String::from)
+        .collect::<Vec<_>>();
+
+    if !imported.is_empty() {
+        let libraries = Api::<RegoLibrary>::all(client.clone())
+            .list(&ListParams::default())
+            .await?
+            .items;
+
+        for library in imported_libraries(imported, &libraries) {
+            check_packages(library)?;
+
+            let name = library.name_any();
+            sources.modules.extend(
+                library
+                    .spec
+                    .modules
+                    .iter()
+                    .map(|(key, source)| (format!("library/{name}/{key}"), source.clone())),
+            );
+            revisions.push(library_revision(library));
+
+            info!(
+                library = %name,
+                package = %library.spec.package,
+                modules = library.spec.modules.len(),
+                "loaded rego library"
+            );
+        }
+    }
+
+    if let Some(reference) = &rule.data_ref {
+        let (namespace, config_map) = get_config_map(labeler, reference, client, config).await?;
+
+        sources
+            .data
+            .push(data_document(config_map_entries(&config_map, reference)?));
+        revisions.push(revision(&namespace, &config_map));
+
+        info!(
+            config_map = %reference.name,
+            config_map_namespace = %namespace,
+            "loaded rego data from dataRef"
+        );
+    }
+
+    sources.revision = (!revisions.is_empty()).then(|| revisions.join(","));
+
+    Ok(sources)
+}
+
+/// Fetches a referenced `ConfigMap`, returns it with its resolved namespace.
+///
+/// # Errors
+///
+/// This function will return an error if the `ConfigMap` can't be found or the `Labeler` isn't
+/// allowed to read its namespace.
+async fn get_config_map<K: LabelerResource>(
+    labeler: &K,
+    reference: &ConfigMapRef,
+    client: &Client,
+    config: &Config,
+) -> Result<(String, ConfigMap)> {
+    let namespace = config_map_namespace(labeler, reference, config)?;
+    let api: Api<ConfigMap> = Api::namespaced(client.clone(), &namespace);
+
+    let config_map = api.get_opt(&reference.name).await?.ok_or_else(|| {
+        Error::PolicyError(format!(
+            "ConfigMap {namespace}/{} referenced by the rego rule not found",
+            reference.name
+        ))
+    })?;
+
+    Ok((namespace, config_map))
+}
+
+/// Builds a data document from `ConfigMap` entries, keys lose their `.json` suffix and values are
+/// parsed as json with a fallback to plain strings.
+fn data_document(entries: Vec<(String, String)>) -> Value {
+    entries
+        .into_iter()
+        .map(|(key, value)| {
+            let key = key.strip_suffix(".json").unwrap_or(&key).to_string();
+            let value = serde_json::from_str(&value).unwrap_or(Value::String(value));
+            (key, value)
+        })
+        .collect::<serde_json::Map<_, _>>()
+        .into()
+}
+
+/// Returns the `ConfigMap`s referenced by the rego rule.
+fn config_map_refs(rule: &RegoRule) -> impl Iterator<Item = &ConfigMapRef> {
+    rule.policy_ref.iter().chain(rule.data_ref.iter())
+}
+
+/// Returns the `Labeler`s of the store referencing the `ConfigMap`, used to map `ConfigMap`
+/// events to reconciliations.
+pub fn referencing<K: LabelerResource, C: Resource>(
+    labelers: &Store<K>,
+    config_map: &C,
+    config: &Config,
+) -> Vec<ObjectRef<K>> {
+    let (name, namespace) = (config_map.name_any(), config_map.namespace());
+
+    labelers
+        .state()
+        .iter()
+        .filter(|labeler| {
+            labeler
+                .spec()
+                .rego
+                .iter()
+                .flat_map(config_map_refs)
+                .any(|reference| {
+                    reference.name == name
+                        && config_map_namespace(labeler.as_ref(), reference, config).ok()
+                            == namespace
+                })
+        })
+        .map(|labeler| ObjectRef::from_obj(labeler.as_ref()))
+        .collect()
+}
+
+/// Returns the `Labeler`s of the store using the library, used to map `RegoLibrary` events to
+/// reconciliations. Besides the `Labeler`s that loaded any generation of the library, the ones
+/// whose inline policy imports the package are returned, so creating a missing library takes
+/// effect right away.
+pub fn importing<K: LabelerResource>(
+    labelers: &Store<K>,
+    library: &RegoLibrary,
+) -> Vec<ObjectRef<K>> {
+    let loaded = format!("RegoLibrary/{}@", library.name_any());
+
+    labelers
+        .state()
+        .iter()
+        .filter(|labeler| {
+            let revision = labeler
+                .status()
+                .and_then(|status| status.policy_revision.as_deref())
+                .unwrap_or_default();
+
+            revision.split(',').any(|r| r.starts_with(&loaded))
+                || labeler.spec().rego.as_ref().is_some_and(|rule| {
+                    imports(&rule.policy).any(|path| imports_package(path, &library.spec.package))
+                })
+        })
+        .map(|labeler| ObjectRef::from_obj(labeler.as_ref()))
+        .collect()
+}
+
+/// Returns the packages a module imports from `data`, e.g. `lib.images` for
+/// `import data.lib.images as images`
+fn imports(source: &str) -> impl Iterator<Item = &str> {
+    source
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("import "))
+        .filter_map(|rest| rest.split_whitespace().next())
+        .filter_map(|path| path.strip_prefix("data."))
+}
+
+/// Returns true if importing the path pulls in the package, either the package itself, one of
+/// its rules or one of its parents are imported.
+fn imports_package(path: &str, package: &str) -> bool {
+    in_package(path, package) || in_package(package, path)
+}
+
+/// Returns true if the path is the package itself or nested under it.
+fn in_package(path: &str, package: &str) -> bool {
+    path.strip_prefix(package)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+}
+
+/// Returns the libraries pulled in by the imported paths sorted by name, including the ones
+/// imported by the modules of other libraries.
+fn imported_libraries(imported: Vec<String>, libraries: &[RegoLibrary]) -> Vec<&RegoLibrary> {
+    let mut pending = imported;
+    let mut loaded: Vec<&RegoLibrary> = Vec::new();
+
+    while let Some(path) = pending.pop() {
+        for library in libraries {
+            if imports_package(&path, &library.spec.package)
+                && !loaded.iter().any(|l| l.name_any() == library.name_any())
+            {
+                pending.extend(
+                    library
+                        .spec
+                        .modules
+                        .values()
+                        .flat_map(|source| imports(source))
+                        .map(String::from),
+                );
+                loaded.push(library);
+            }
+        }
+    }
+
+    loaded.sort_by_key(|library| library.name_any());
+    loaded
+}
+
+/// Checks that every module of the library declares the library's package or a sub package.
+///
+/// # Errors
+///
+/// This function will return an error for the first module declaring a different package.
+fn check_packages(library: &RegoLibrary) -> Result<()> {
+    let package = &library.spec.package;
+
+    for (key, source) in &library.spec.modules {
+        let declared = source
+            .lines()
+            .find_map(|line| line.trim().strip_prefix("package "))
+            .map(str::trim);
+
+        if !declared.is_some_and(|declared| in_package(declared, package)) {
+            return Err(Error::PolicyError(format!(
+                "Module {key} of RegoLibrary {} must declare package {package}",
+                library.name_any()
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+/// Identifies the loaded version of a library, e.g. `RegoLibrary/images@3`
+fn library_revision(library: &RegoLibrary) -> String {
+    format!(
+        "RegoLibrary/{}@{}",
+        library.name_any(),
+        library.metadata.generation.unwrap_or_default()
+    )
+}
+
+/// Resolves the namespace of a referenced `ConfigMap`, defaults to the `Labeler`'s namespace.
+///
+/// # Errors
+///
+/// This function will return an error if the namespace is missing for a `ClusterLabeler` or the
+/// `Labeler` is not allowed to reference other namespaces.
+fn config_map_namespace<K: LabelerResource>(
+    labeler: &K,
+    reference: &ConfigMapRef,
+    config: &Config,
+) -> Result<String> {
+    let own = labeler.namespace();
+
+    match (reference.namespace.as_ref(), own) {
+        (Some(ns), Some(own)) if *ns != own && !config.allows_cluster_wide(&own) => {
+            Err(Error::PolicyError(format!(
+                "Labelers in namespace {own} may not reference ConfigMaps in namespace {ns}"
+            )))
+        }
+        (Some(ns), _) => Ok(ns.clone()),
+        (None, Some(own)) => Ok(own),
+        (None, None) => Err(Error::PolicyError(format!(
+            "ConfigMap {} must have a namespace",
+            reference.name
+        ))),
+    }
+}
+
+/// Returns the requested keys of the `ConfigMap` with their values, every key if none are listed.
+///
+/// # Errors
+///
+/// This function will return an error if any of the requested keys is missing.
+fn config_map_entries(
+    config_map: &ConfigMap,
+    reference: &ConfigMapRef,
+) -> Result<Vec<(String, String)>> {
+    let data = config_map.data.clone().unwrap_or_default();
+
+    if reference.keys.is_empty() {
+        return Ok(data.into_iter().collect());
+    }
+
+    reference
+        .keys
+        .iter()
+        .map(|key| {
+            data.get(key)
+                .map(|value| (key.clone(), value.clone()))
+                .ok_or_else(|| {
+                    Error::PolicyError(format!(
+                        "Key {key} not found in ConfigMap {}",
+                        reference.name
+                    ))
+                })
+        })
+        .collect()
+}
+
+/// Identifies the loaded version of an object, e.g. `stickerbomb/policies@1234`
+fn revision<K: ResourceExt>(namespace: &str, object: &K) -> String {
+    format!(
+        "{namespace}/{}@{}",
+        object.name_any(),
+        object.resource_version().unwrap_or_default()
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use kube::api::ObjectMeta;
+    use serde_json::json;
+    use stickerbomb_crd::Labeler;
+
+    use super::*;
+
+    fn config_map() -> ConfigMap {
+        ConfigMap {
+            metadata: ObjectMeta {
+                name: Some("policies".to_string()),
+                namespace: Some("team-a".to_string()),
+                resource_version: Some("42".to_string()),
+                ..ObjectMeta::default()
+            },
+            data: Some(BTreeMap::from([
+                ("images.rego".to_string(), "package images".to_string()),
+                ("owners.rego".to_string(), "package owners".to_string()),
+            ])),
+            ..ConfigMap::default()
+        }
+    }
+
+    fn labeler(namespace: Option<&str>) -> Labeler {
+        serde_json::from_value(json!({
+            "apiVersion": "stickerbomb.dev/v1alpha1",
+            "kind": "Labeler",
+            "metadata": {"name": "test", "namespace": "team-a"},
+            "spec": {
+                "resourceApi": "v1",
+                "resourceKind": "Pod",
+                "labels": {"team": "a"},
+                "rego": {
+                    "policyRef": {"name": "policies", "namespace": namespace},
+                    "query": "data.images.allow"
+                }
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_config_map_namespace() {
+        let config = Config::default();
+        let reference = |ns: Option<&str>| ConfigMapRef {
+            name: "policies".to_string(),
+            namespace: ns.map(String::from),
+            keys: Vec::new(),
+        };
+
+        assert_eq!(
+            config_map_namespace(&labeler(None), &reference(None), &config).unwrap(),
+            "team-a"
+        );
+        assert!(config_map_namespace(&labeler(None), &reference(Some("team-b")), &config).is_err());
+
+        let config = Config {
+            cluster_wide_namespaces: vec!["team-a".to_string()],
+            ..Config::default()
+        };
+        assert_eq!(
+            config_map_namespace(&labeler(None), &reference(Some("team-b")), &config).unwrap(),
+            "team-b"
+        );
+    }
+
+    #[test]
+    fn test_config_map_entries() {
+        let mut reference = ConfigMapRef {
+            name: "policies".to_string(),
+            namespace: None,
+            keys: Vec::new(),
+        };
+        assert_eq!(
+            config_map_entries(&config_map(), &reference).unwrap().len(),
+            2
+        );
+
+        reference.keys = vec!["owners.rego".to_string()];
+        assert_eq!(
+            config_map_entries(&config_map(), &reference).unwrap(),
+            vec![("owners.rego".to_string(), "package owners".to_string())]
+        );
+
+        reference.keys = vec!["missing.rego".to_string()];
+        assert!(config_map_entries(&config_map(), &reference).is_err());
+
+        assert_eq!(revision("team-a", &config_map()), "team-a/policies@42");
+    }
+
+    #[test]
+    fn test_data_document() {
+        let document = data_document(vec![
+            (
+                "registries.json".to_string(),
+                r#"["ghcr.io", "quay.io"]"#.to_string(),
+            ),
+            ("team".to_string(), "platform".to_string()),
+        ]);
+        assert_eq!(
+            document,
+            json!({"registries": ["ghcr.io", "quay.io"], "team": "platform"})
+        );
+
+        let sources = PolicySources {
+            modules: vec![(
+                "test.rego".to_string(),
+                "package test\ntrusted := \"ghcr.io\" in data.registries".to_string(),
+            )],
+            data: vec![document, json!({"limits": {"max": 3}})],
+            revision: None,
+        };
+        let mut engine = Engine::new();
+        sources.add_to(&mut engine).unwrap();
+        assert!(
+            engine
+                .eval_bool_query("data.test.trusted".to_string(), false)
+                .unwrap()
+        );
+    }
+
+    fn library(name: &str, package: &str, source: &str) -> RegoLibrary {
+        let mut library = RegoLibrary::new(
+            name,
+            stickerbomb_crd::v1_alpha1::RegoLibrarySpec {
+                package: package.to_string(),
+                modules: BTreeMap::from([("lib.rego".to_string(), source.to_string())]),
+            },
+        );
+        library.metadata.generation = Some(3);
+        library
+    }
+
+    #[test]
+    fn test_imported_libraries() {
+        let policy = "package test\nimport rego.v1\nimport data.lib.images as images\n";
+        assert_eq!(imports(policy).collect::<Vec<_>>(), vec!["lib.images"]);
+
+        assert!(imports_package("lib.images", "lib.images"));
+        assert!(imports_package("lib.images.registry", "lib.images"));
+        assert!(imports_package("lib", "lib.images"));
+        assert!(!imports_package("lib.imagesx", "lib.images"));
+
+        let libraries = vec![
+            library(
+                "images",
+                "lib.images",
+                "package lib.images\nimport data.lib.strings\nregistry(image) := split(image, \"/\")[0]",
+            ),
+            library(
+                "strings",
+                "lib.strings",
+                "package lib.strings\nupper := true",
+            ),
+            library("owners", "lib.owners", "package lib.owners\nteam := \"a\""),
+        ];
+        let names = imported_libraries(imports(policy).map(String::from).collect(), &libraries)
+            .into_iter()
+            .map(ResourceExt::name_any)
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec!["images", "strings"]);
+
+        assert_eq!(library_revision(&libraries[0]), "RegoLibrary/images@3");
+    }
+
+    #[test]
+    fn test_check_packages() {
+        assert!(check_packages(&library("images", "lib.images", "package lib.images")).is_ok());
+        assert!(check_packages(&library("images", "lib.images", "package lib.images.oci")).is_ok());
+        assert!(check_packages(&library("images", "lib.images", "package lib.imagesx")).is_err());
+        assert!(check_packages(&library("images", "lib.images", "x := 1")).is_err());
+    }
+
+    #[test]
+    fn test_importing() {
+        let (store, mut writer) = kube::runtime::reflector::store::<Labeler>();
+        let mut inline = labeler(None);
+        if let Some(rule) = inline.spec.rego.as_mut() {
+            rule.policy = "package test\nimport data.lib.images\n".to_string();
+        }
+        writer.apply_watcher_event(&kube::runtime::watcher::Event::Apply(inline));
+        let mut loaded = labeler(None);
+        loaded.metadata.name = Some("loaded".to_string());
+        loaded.status = Some(stickerbomb_crd::LabelerStatus {
+            policy_revision: Some("team-a/policies@42,RegoLibrary/images@2".to_string()),
+            ..Default::default()
+        });
+        writer.apply_watcher_event(&kube::runtime::watcher::Event::Apply(loaded));
+        let mut unrelated = labeler(None);
+        unrelated.metadata.name = Some("unrelated".to_string());
+        writer.apply_watcher_event(&kube::runtime::watcher::Event::Apply(unrelated));
+
+        let mut refs = importing(
+            &store,
+            &library("images", "lib.images", "package lib.images"),
+        )
+        .into_iter()
+        .map(|r| r.name)
+        .collect::<Vec<_>>();
+        refs.sort();
+        assert_eq!(refs, vec!["loaded", "test"]);
+    }
+
+    #[test]
+    fn test_referencing() {
+        let (store, mut writer) = kube::runtime::reflector::store::<Labeler>();
+        writer.apply_watcher_event(&kube::runtime::watcher::Event::Apply(labeler(None)));
+        let mut other = labeler(Some("team-b"));
+        other.metadata.name = Some("other".to_string());
+        writer.apply_watcher_event(&kube::runtime::watcher::Event::Apply(other));
+        let mut data = labeler(None);
+        data.metadata.name = Some("data".to_string());
+        if let Some(rule) = data.spec.rego.as_mut() {
+            rule.data_ref = rule.policy_ref.take();
+        }
+        writer.apply_watcher_event(&kube::runtime::watcher::Event::Apply(data));
+
+        let config = Config::default();
+        let mut refs = referencing(&store, &config_map(), &config)
+            .into_iter()
+            .map(|r| r.name)
+            .collect::<Vec<_>>();
+        refs.sort();
+        assert_eq!(refs, vec!["data", "test"]);
+    }
+}
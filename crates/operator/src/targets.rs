@@ -0,0 +1,664 @@
+// Copyright 2026 Stickerbomb Maintainers
+// SPDX-License-Identifier: Apache-2.0
+
+//! Watches the kinds targeted by `Labeler`s and `ClusterLabeler`s.
+//!
+//! Objects that are created or change their labels, annotations or generation are labeled by the
+//! `Labeler`s targeting them within seconds, instead of waiting for the next scheduled
+//! reconciliation. Only the changed objects are fetched and labeled, the other targets aren't
+//! listed. Changes that only touch the status or keys applied by `Labeler`s are ignored, so the
+//! writes of a `Labeler` don't trigger it again and `Labeler`s overwriting each other's keys only
+//! meet on their scheduled reconciliations. Only the metadata of the objects is watched and only in
+//! the namespaces the `Labeler`s target. Changed objects are collected and labeled once per
+//! `TRIGGER_INTERVAL`, so an object changing repeatedly is fetched once.
+
+use std::collections::btree_map::Entry;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::Namespace;
+use kube::api::{DynamicObject, ObjectMeta};
+use kube::core::PartialObjectMeta;
+use kube::core::gvk::GroupVersionKind;
+use kube::discovery::{ApiCapabilities, ApiResource, Scope};
+use kube::runtime::reflector::{self, ObjectRef, Store};
+use kube::runtime::{WatchStreamExt, metadata_watcher, watcher};
+use kube::{Api, Client, ResourceExt};
+use regorus::Engine;
+use stickerbomb_crd::v1_alpha1::TargetResource;
+use stickerbomb_crd::{ClusterLabeler, Labeler};
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
+use tracing::{debug, info, warn};
+
+use crate::Result;
+use crate::config::Config;
+use crate::controller::{
+    Context, LabelerResource, TargetScope, admission_engine, discover_target_resources,
+    label_object, labeler_keys, labels_object, watched_target_scope,
+};
+
+/// Time between two checks of the kinds targeted by the `Labeler`s
+const SYNC_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Time changed objects are collected for before they are labeled
+const TRIGGER_INTERVAL: Duration = Duration::from_secs(5);
+
+/// `resourceApi`, `resourceKind` and namespace (`None` for every namespace) of a target watcher
+type WatchKey = (String, String, Option<String>);
+
+/// Reflector store of the namespaces the namespace selectors are matched against
+type Namespaces = Store<PartialObjectMeta<Namespace>>;
+
+/// Changed objects by the `Labeler`s targeting them
+type Pending<K> = HashMap<ObjectRef<K>, HashSet<ObjectRef<DynamicObject>>>;
+
+/// `Labeler`s of a kind and the changed objects they target
+pub struct Triggers<K: LabelerResource> {
+    labelers: Store<K>,
+    /// Objects that changed since the last flush
+    pending: Arc<Mutex<Pending<K>>>,
+}
+
+impl<K: LabelerResource> Clone for Triggers<K> {
+    fn clone(&self) -> Self {
+        Self {
+            labelers: self.labelers.clone(),
+            pending: self.pending.clone(),
+        }
+    }
+}
+
+impl<K: LabelerResource> Triggers<K> {
+    /// Creates the triggers of the `Labeler`s in the store.
+    pub fn new(labelers: Store<K>) -> Self {
+        Self {
+            labelers,
+            pending: Arc::default(),
+        }
+    }
+
+    /// Marks the object for labeling on the next flush by every `Labeler` targeting it.
+    fn send(&self, ar: &ApiResource, meta: &ObjectMeta, namespaces: &Namespaces, config: &Config) {
+        let kind = GroupVersionKind::gvk(&ar.group, &ar.version, &ar.kind);
+
+        for labeler in self.labelers.state() {
+            // Invalid selectors are reported by the reconciliation
+            if !labels_object(labeler.as_ref(), &kind, meta, namespaces, config).unwrap_or(false) {
+                continue;
+            }
+
+            debug!(
+                labeler_name = %labeler.name_any(),
+                labeler_namespace = labeler.namespace().as_deref(),
+                target_resource = meta.name.as_deref(),
+                target_namespace = meta.namespace.as_deref(),
+                target_kind = %kind.kind,
+                "target object changed, labeling it on the next flush"
+            );
+
+            let mut object =
+                ObjectRef::new_with(meta.name.as_deref().unwrap_or_default(), ar.clone());
+            object.namespace.clone_from(&meta.namespace);
+
+            self.pending
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner)
+                .entry(ObjectRef::from_obj(labeler.as_ref()))
+                .or_default()
+                .insert(object);
+        }
+    }
+
+    /// Labels the objects marked since the last flush with the `Labeler`s targeting them, the
+    /// policy of every `Labeler` is compiled once per flush. Failures are left to the reconciliation.
+    async fn flush(&self, ctx: &Context, namespaces: &Namespaces) {
+        let pending = std::mem::take(
+            &mut *self
+                .pending
+                .lock()
+                .unwrap_or_else(std::sync::PoisonError::into_inner),
+        );
+
+        for (labeler, objects) in pending {
+            // `Labeler`s deleted since the objects changed are skipped
+            let Some(labeler) = self.labelers.get(&labeler) else {
+                continue;
+            };
+
+            let mut engine =
+                match admission_engine(labeler.as_ref(), &ctx.client, &ctx.config).await {
+                    Ok(engine) => engine,
+                    Err(e) => {
+                        warn!(
+                            error = %e,
+                            labeler_name = %labeler.name_any(),
+                            labeler_namespace = labeler.namespace().as_deref(),
+                            "unable to compile policy for changed targets"
+                        );
+                        continue;
+                    }
+                };
+
+            for object in objects {
+                if let Err(e) =
+                    label_changed(labeler.as_ref(), ctx, &object, namespaces, &mut engine).await
+                {
+                    warn!(
+                        error = %e,
+                        labeler_name = %labeler.name_any(),
+                        labeler_namespace = labeler.namespace().as_deref(),
+                        target_resource = %object.name,
+                        target_namespace = object.namespace.as_deref(),
+                        target_kind = %object.dyntype.kind,
+                        "unable to label changed target"
+                    );
+                }
+            }
+        }
+    }
+}
+
+/// Fetches a changed object and labels it if the `Labeler` still targets it, objects deleted since
+/// they changed are skipped.
+///
+/// # Errors
+///
+/// This function will return an error if the object can't be fetched or labeled.
+async fn label_changed<K: LabelerResource>(
+    labeler: &K,
+    ctx: &Context,
+    object: &ObjectRef<DynamicObject>,
+    namespaces: &Namespaces,
+    engine: &mut Engine,
+) -> Result<()> {
+    let ar = &object.dyntype;
+    let api: Api<DynamicObject> = match &object.namespace {
+        Some(namespace) => Api::namespaced_with(ctx.client.clone(), namespace, ar),
+        None => Api::all_with(ctx.client.clone(), ar),
+    };
+    let Some(object) = api.get_opt(&object.name).await? else {
+        return Ok(());
+    };
+
+    let kind = GroupVersionKind::gvk(&ar.group, &ar.version, &ar.kind);
+    if !labels_object(labeler, &kind, &object.metadata, namespaces, &ctx.config)? {
+        return Ok(());
+    }
+
+    label_object(labeler, ctx, ar, &object, engine).await
+}
+
+/// Runs a watcher for every kind and namespace targeted by any `Labeler` or `ClusterLabeler` until
+/// the leadership is lost, watchers are started and stopped as `Labeler`s come and go. The
+/// collected objects are labeled every `TRIGGER_INTERVAL`.
+pub async fn watch(
+    labelers: Triggers<Labeler>,
+    cluster_labelers: Triggers<ClusterLabeler>,
+    ctx: Arc<Context>,
+    mut leader_rx: watch::Receiver<bool>,
+) {
+    let (namespaces, writer) = reflector::store();
+    let namespaces_reflector = tokio::spawn(
+        reflector::reflector(
+            writer,
+            metadata_watcher(
+                Api::<Namespace>::all(ctx.client.clone()),
+                watcher::Config::default().any_semantic(),
+            ),
+        )
+        .default_backoff()
+        .for_each(|_| futures::future::ready(())),
+    );
+
+    let mut watchers = Watchers::default();
+    let mut sync = tokio::time::interval(SYNC_INTERVAL);
+    sync.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut flush = tokio::time::interval(TRIGGER_INTERVAL);
+    flush.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = sync.tick() => {
+                watchers
+                    .sync(&labelers, &cluster_labelers, &namespaces, &ctx)
+                    .await;
+            }
+            _ = flush.tick() => {
+                cluster_labelers.flush(&ctx, &namespaces).await;
+                labelers.flush(&ctx, &namespaces).await;
+            }
+            _ = leader_rx.wait_for(|&is_leader| !is_leader) => break,
+        }
+    }
+
+    for handle in watchers.watchers.values() {
+        handle.abort();
+    }
+    namespaces_reflector.abort();
+}
+
+/// Running target watchers and the discovered kinds they watch
+#[derive(Default)]
+struct Watchers {
+    discovered: BTreeMap<(String, String), (ApiResource, ApiCapabilities)>,
+    watchers: BTreeMap<WatchKey, JoinHandle<()>>,
+}
+
+impl Watchers {
+    /// Starts a watcher for every kind and namespace targeted by the `Labeler`s and stops the ones
+    /// that aren't targeted anymore.
+    async fn sync(
+        &mut self,
+        labelers: &Triggers<Labeler>,
+        cluster_labelers: &Triggers<ClusterLabeler>,
+        namespaces: &Namespaces,
+        ctx: &Context,
+    ) {
+        let client = &ctx.client;
+        let targets = targeted_kinds(&labelers.labelers, &cluster_labelers.labelers);
+        self.discovered
+            .retain(|target, _| targets.contains_key(target));
+
+        let mut wanted: BTreeMap<WatchKey, ApiResource> = BTreeMap::new();
+        for ((api, kind), scope) in targets {
+            let (ar, caps) = match self.discovered.entry((api.clone(), kind.clone())) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => {
+                    let target = TargetResource {
+                        resource_api: api.clone(),
+                        resource_kind: kind.clone(),
+                    };
+
+                    // Kinds that can't be discovered are retried on the next sync
+                    match discover_target_resources(&target, client).await {
+                        Ok(resource) => entry.insert(resource),
+                        Err(e) => {
+                            debug!(
+                                error = %e,
+                                resource_api = %api,
+                                resource_kind = %kind,
+                                "unable to discover target kind"
+                            );
+                            continue;
+                        }
+                    }
+                }
+            };
+
+            match (&caps.scope, scope) {
+                (Scope::Cluster, _) | (_, TargetScope::Cluster) => {
+                    wanted.insert((api, kind, None), ar.clone());
+                }
+                (Scope::Namespaced, TargetScope::Namespaces(namespaces)) => {
+                    for namespace in namespaces {
+                        wanted.insert((api.clone(), kind.clone(), Some(namespace)), ar.clone());
+                    }
+                }
+            }
+        }
+
+        self.watchers.retain(|(api, kind, namespace), handle| {
+            let targeted = wanted.contains_key(&(api.clone(), kind.clone(), namespace.clone()));
+            if !targeted {
+                info!(
+                    resource_api = %api,
+                    resource_kind = %kind,
+                    namespace = namespace.as_deref(),
+                    "stopping target watcher"
+                );
+                handle.abort();
+            }
+            targeted
+        });
+
+        for (key, ar) in wanted {
+            if self.watchers.contains_key(&key) {
+                continue;
+            }
+
+            let (api, kind, namespace) = &key;
+            info!(
+                resource_api = %api,
+                resource_kind = %kind,
+                namespace = namespace.as_deref(),
+                "starting target watcher"
+            );
+            let handle = tokio::spawn(watch_kind(
+                ar,
+                namespace.clone(),
+                client.clone(),
+                labelers.clone(),
+                cluster_labelers.clone(),
+                namespaces.clone(),
+                ctx.config.clone(),
+            ));
+            self.watchers.insert(key, handle);
+        }
+    }
+}
+
+/// Collects the `resourceApi` and `resourceKind` pairs targeted by the `Labeler`s that aren't
+/// suspended, in dry-run mode or being deleted, with the namespaces they are targeted in.
+fn targeted_kinds(
+    labelers: &Store<Labeler>,
+    cluster_labelers: &Store<ClusterLabeler>,
+) -> BTreeMap<(String, String), TargetScope> {
+    let mut targets = BTreeMap::new();
+    collect_targets(&mut targets, labelers);
+    collect_targets(&mut targets, cluster_labelers);
+    targets
+}
+
+/// Adds the kinds targeted by the `Labeler`s of the store to `targets`, the scopes of `Labeler`s
+/// targeting the same kind are merged.
+fn collect_targets<K: LabelerResource>(
+    targets: &mut BTreeMap<(String, String), TargetScope>,
+    labelers: &Store<K>,
+) {
+    for labeler in labelers.state() {
+        if !watches_targets(labeler.as_ref()) {
+            continue;
+        }
+
+        let spec = labeler.spec();
+
+        let scope = watched_target_scope(labeler.as_ref());
+        for target in spec.targets() {
+            let key = (target.resource_api, target.resource_kind);
+            match (targets.get_mut(&key), &scope) {
+                (Some(TargetScope::Namespaces(namespaces)), TargetScope::Namespaces(more)) => {
+                    namespaces.extend(more.iter().cloned());
+                }
+                (Some(merged), _) => *merged = TargetScope::Cluster,
+                (None, scope) => {
+                    targets.insert(key, scope.clone());
+                }
+            }
+        }
+    }
+}
+
+/// Returns true if changed targets should be labeled by the `Labeler`, suspended and dry-run
+/// `Labeler`s don't label anything, `Labeler`s being deleted would put back the keys their cleanup
+/// removes and field selectors are only evaluated by the API server.
+fn watches_targets<K: LabelerResource>(labeler: &K) -> bool {
+    let spec = labeler.spec();
+    !spec.suspend
+        && !spec.dry_run
+        && spec.field_selector.is_none()
+        && labeler.meta().deletion_timestamp.is_none()
+}
+
+/// Watches the metadata of the objects of a kind in a namespace (or every namespace), objects are
+/// labeled when their fingerprint changed since the last event. The initial list is left to the
+/// scheduled reconciliations.
+async fn watch_kind(
+    ar: ApiResource,
+    namespace: Option<String>,
+    client: Client,
+    labelers: Triggers<Labeler>,
+    cluster_labelers: Triggers<ClusterLabeler>,
+    namespaces: Namespaces,
+    config: Config,
+) {
+    let api: Api<DynamicObject> = match &namespace {
+        Some(namespace) => Api::namespaced_with(client, namespace, &ar),
+        None => Api::all_with(client, &ar),
+    };
+    let mut seen: HashMap<String, u64> = HashMap::new();
+
+    let mut events = metadata_watcher(api, watcher::Config::default().any_semantic())
+        .default_backoff()
+        .boxed();
+
+    while let Some(event) = events.next().await {
+        match event {
+            Ok(watcher::Event::Apply(object)) => {
+                let uid = object.uid().unwrap_or_default();
+                let fingerprint = fingerprint(&object.metadata);
+
+                if seen.insert(uid, fingerprint) == Some(fingerprint) {
+                    continue;
+                }
+
+                cluster_labelers.send(&ar, &object.metadata, &namespaces, &config);
+                labelers.send(&ar, &object.metadata, &namespaces, &config);
+            }
+            Ok(watcher::Event::InitApply(object)) => {
+                seen.insert(
+                    object.uid().unwrap_or_default(),
+                    fingerprint(&object.metadata),
+                );
+            }
+            Ok(watcher::Event::Delete(object)) => {
+                seen.remove(&object.uid().unwrap_or_default());
+            }
+            Ok(watcher::Event::Init | watcher::Event::InitDone) => {}
+            Err(e) => warn!(
+                error = %e,
+                kind = %ar.kind,
+                namespace = namespace.as_deref(),
+                "target watcher failed"
+            ),
+        }
+    }
+}
+
+/// Hashes the parts of an object a `Labeler` reacts to, status only changes keep the fingerprint.
+/// The keys applied by the field managers of `Labeler`s are left out, so setting or removing them
+/// doesn't label the object again.
+fn fingerprint(meta: &ObjectMeta) -> u64 {
+    let (labeler_labels, labeler_annotations) = labeler_keys(meta);
+    let mut hasher = DefaultHasher::new();
+    meta.generation.hash(&mut hasher);
+    for (entries, applied) in [
+        (&meta.labels, labeler_labels),
+        (&meta.annotations, labeler_annotations),
+    ] {
+        entries
+            .iter()
+            .flatten()
+            .filter(|(key, _)| !applied.contains(*key))
+            .collect::<Vec<_>>()
+            .hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use k8s_openapi::api::batch::v1::Job;
+    use k8s_openapi::api::core::v1::Pod;
+    use k8s_openapi::apimachinery::pkg::apis::meta::v1::{
+        FieldsV1, LabelSelector, ManagedFieldsEntry, Time,
+    };
+    use k8s_openapi::chrono::Utc;
+    use kube::runtime::reflector;
+    use kube::runtime::watcher::Event;
+    use serde_json::json;
+
+    use super::*;
+
+    fn labeler(name: &str, kind: &str, suspend: bool) -> Labeler {
+        serde_json::from_value(json!({
+            "apiVersion": "stickerbomb.dev/v1alpha1",
+            "kind": "Labeler",
+            "metadata": {"name": name, "namespace": "team-a"},
+            "spec": {
+                "resourceApi": "apps/v1",
+                "resourceKind": kind,
+                "targets": [{"resourceApi": "v1", "resourceKind": "Pod"}],
+                "labels": {"team": "a"},
+                "suspend": suspend
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_targeted_kinds() {
+        let (labelers, mut writer) = reflector::store();
+        writer.apply_watcher_event(&Event::Apply(labeler("deployments", "Deployment", false)));
+        writer.apply_watcher_event(&Event::Apply(labeler("jobs", "StatefulSet", true)));
+
+        let (cluster_labelers, mut writer) = reflector::store();
+        let cluster_labeler: ClusterLabeler = serde_json::from_value(json!({
+            "apiVersion": "stickerbomb.dev/v1alpha1",
+            "kind": "ClusterLabeler",
+            "metadata": {"name": "pods"},
+            "spec": {
+                "resourceApi": "v1",
+                "resourceKind": "Pod",
+                "targetNamespaces": ["team-b"],
+                "labels": {"team": "b"}
+            }
+        }))
+        .unwrap();
+        writer.apply_watcher_event(&Event::Apply(cluster_labeler));
+
+        let namespaces = |namespaces: &[&str]| {
+            TargetScope::Namespaces(namespaces.iter().map(ToString::to_string).collect())
+        };
+        assert_eq!(
+            targeted_kinds(&labelers, &cluster_labelers),
+            BTreeMap::from([
+                (
+                    ("apps/v1".to_string(), "Deployment".to_string()),
+                    namespaces(&["team-a"])
+                ),
+                (
+                    ("v1".to_string(), "Pod".to_string()),
+                    namespaces(&["team-a", "team-b"])
+                ),
+            ])
+        );
+
+        let mut selecting = labeler("selecting", "Deployment", false);
+        selecting.spec.namespace_selector = Some(LabelSelector::default());
+        let (labelers, mut writer) = reflector::store();
+        writer.apply_watcher_event(&Event::Apply(selecting));
+        writer.apply_watcher_event(&Event::Apply(labeler("deployments", "Deployment", false)));
+        let (cluster_labelers, _) = reflector::store();
+
+        assert_eq!(
+            targeted_kinds(&labelers, &cluster_labelers)
+                [&("apps/v1".to_string(), "Deployment".to_string())],
+            TargetScope::Cluster
+        );
+    }
+
+    #[test]
+    fn test_triggers_send() {
+        let mut labeler = labeler("pods", "Deployment", false);
+        labeler.metadata.uid = Some("1234".to_string());
+        labeler.spec.selector =
+            Some(serde_json::from_value(json!({"matchLabels": {"app": "web"}})).unwrap());
+        let mut dry_run = labeler.clone();
+        dry_run.metadata.name = Some("dry-run".to_string());
+        dry_run.spec.dry_run = true;
+        let mut deleting = labeler.clone();
+        deleting.metadata.name = Some("deleting".to_string());
+        deleting.metadata.deletion_timestamp = Some(Time(Utc::now()));
+
+        let (labelers, mut writer) = reflector::store();
+        for labeler in [&labeler, &dry_run, &deleting] {
+            writer.apply_watcher_event(&Event::Apply(labeler.clone()));
+        }
+        let triggers = Triggers::new(labelers);
+        let (namespaces, _) = reflector::store();
+        let config = Config::default();
+        let pods = ApiResource::erase::<Pod>(&());
+        let pod = |name: &str, namespace: &str, app: &str| ObjectMeta {
+            name: Some(name.to_string()),
+            namespace: Some(namespace.to_string()),
+            labels: Some(BTreeMap::from([("app".to_string(), app.to_string())])),
+            ..ObjectMeta::default()
+        };
+
+        triggers.send(&pods, &pod("web", "team-a", "web"), &namespaces, &config);
+        triggers.send(&pods, &pod("web", "team-a", "web"), &namespaces, &config);
+        triggers.send(&pods, &pod("api", "team-a", "web"), &namespaces, &config);
+        triggers.send(&pods, &pod("db", "team-a", "db"), &namespaces, &config);
+        triggers.send(&pods, &pod("web", "team-b", "web"), &namespaces, &config);
+        triggers.send(
+            &ApiResource::erase::<Job>(&()),
+            &pod("web", "team-a", "web"),
+            &namespaces,
+            &config,
+        );
+
+        // Objects changing repeatedly are only labeled once by the targeting `Labeler`s
+        let pending = triggers.pending.lock().unwrap();
+        let object = |name: &str| ObjectRef::new_with(name, pods.clone()).within("team-a");
+        assert_eq!(
+            *pending,
+            HashMap::from([(
+                ObjectRef::from_obj(&labeler),
+                HashSet::from([object("web"), object("api")])
+            )])
+        );
+    }
+
+    #[test]
+    fn test_fingerprint() {
+        let mut meta = ObjectMeta {
+            name: Some("web".to_string()),
+            labels: Some(BTreeMap::from([("app".to_string(), "web".to_string())])),
+            resource_version: Some("1".to_string()),
+            ..ObjectMeta::default()
+        };
+        let before = fingerprint(&meta);
+
+        // Status updates only bump the resource version
+        meta.resource_version = Some("2".to_string());
+        assert_eq!(fingerprint(&meta), before);
+
+        meta.labels
+            .get_or_insert_default()
+            .insert("team".to_string(), "a".to_string());
+        assert_ne!(fingerprint(&meta), before);
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_labeler_writes() {
+        let applied = |manager: &str, operation: &str| ManagedFieldsEntry {
+            manager: Some(manager.to_string()),
+            operation: Some(operation.to_string()),
+            fields_type: Some("FieldsV1".to_string()),
+            fields_v1: Some(FieldsV1(
+                json!({"f:metadata": {"f:labels": {"f:team": {}}}}),
+            )),
+            ..ManagedFieldsEntry::default()
+        };
+        let mut meta = ObjectMeta {
+            name: Some("web".to_string()),
+            labels: Some(BTreeMap::from([("app".to_string(), "web".to_string())])),
+            ..ObjectMeta::default()
+        };
+        let before = fingerprint(&meta);
+
+        // The `Labeler`'s own apply doesn't label the object again
+        meta.labels
+            .get_or_insert_default()
+            .insert("team".to_string(), "a".to_string());
+        meta.managed_fields = Some(vec![applied("stickerbomb/team-a/pods", "Apply")]);
+        assert_eq!(fingerprint(&meta), before);
+
+        // Neither does another `Labeler` overwriting the key
+        meta.labels
+            .get_or_insert_default()
+            .insert("team".to_string(), "b".to_string());
+        meta.managed_fields = Some(vec![applied("stickerbomb/team-b/pods", "Apply")]);
+        assert_eq!(fingerprint(&meta), before);
+
+        // Keys changed by anyone else do
+        meta.managed_fields = Some(vec![applied("kubectl", "Update")]);
+        assert_ne!(fingerprint(&meta), before);
+    }
+}
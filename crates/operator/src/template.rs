@@ -0,0 +1,353 @@
+// Copyright 2026 Stickerbomb Maintainers
+// SPDX-License-Identifier: Apache-2.0
+
+//! Minimal templating for label and annotation values rendered from the target object.
+//!
+//! Expressions are wrapped in `{{ }}` and contain a path into the target's json representation,
+//! optionally followed by filters, e.g. `{{ metadata.namespace }}` or
+//! `{{ spec.containers[0].image | registry }}`.
+//! Quoted keys can be used for fields containing dots: `{{ metadata.labels["app.kubernetes.io/name"] }}`.
+
+use serde_json::Value;
+
+/// Maximum length of a kubernetes label value
+pub const MAX_LABEL_VALUE_LENGTH: usize = 63;
+
+/// Registry used by container runtimes for images without an explicit registry
+const DEFAULT_REGISTRY: &str = "docker.io";
+
+/// Errors that can occur while rendering a template
+#[derive(thiserror::Error, Debug, PartialEq)]
+pub enum Error {
+    /// Expression is missing its closing braces
+    #[error("unclosed template expression in {0:?}")]
+    Unclosed(String),
+
+    /// Path can't be parsed
+    #[error("invalid path {0:?}")]
+    InvalidPath(String),
+
+    /// Path doesn't exist on the target object
+    #[error("no value found at {0:?}")]
+    MissingValue(String),
+
+    /// Path points to an object or an array
+    #[error("value at {0:?} is not a string, number or boolean")]
+    NotScalar(String),
+
+    /// Filter is not supported
+    #[error("unknown filter {0:?}, supported filters: lower, upper, registry, repository, tag")]
+    UnknownFilter(String),
+
+    /// Rendered value can't be turned into a valid label value
+    #[error("{0:?} cannot be converted to a valid label value")]
+    InvalidLabelValue(String),
+}
+
+/// A single step in a path expression
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Key(&'a str),
+    Index(usize),
+}
+
+/// Returns true if the value contains a template expression.
+#[must_use]
+pub fn is_template(value: &str) -> bool {
+    value.contains("{{")
+}
+
+/// Renders every `{{ }}` expression in `template` against `input`, text outside of the
+/// expressions is kept as is.
+///
+/// # Errors
+///
+/// This function will return an error if an expression is malformed, points to a missing or
+/// non-scalar value or uses an unknown filter.
+pub fn render(template: &str, input: &Value) -> Result<String, Error> {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        rendered.push_str(&rest[..start]);
+
+        let expression = &rest[start + 2..];
+        let end = expression
+            .find("}}")
+            .ok_or_else(|| Error::Unclosed(template.to_string()))?;
+
+        rendered.push_str(&evaluate(expression[..end].trim(), input)?);
+        rest = &expression[end + 2..];
+    }
+
+    rendered.push_str(rest);
+
+    Ok(rendered)
+}
+
+/// Turns any string into a valid label value by replacing invalid characters with `-`, truncating
+/// it to 63 characters and trimming non-alphanumeric characters from both ends.
+///
+/// # Errors
+///
+/// This function will return an error if nothing is left of a non-empty value.
+pub fn sanitize_label_value(value: &str) -> Result<String, Error> {
+    let replaced: String = value
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                c
+            } else {
+                '-'
+            }
+        })
+        .take(MAX_LABEL_VALUE_LENGTH)
+        .collect();
+
+    let sanitized = replaced.trim_matches(|c: char| !c.is_ascii_alphanumeric());
+
+    if sanitized.is_empty() && !value.is_empty() {
+        return Err(Error::InvalidLabelValue(value.to_string()));
+    }
+
+    Ok(sanitized.to_string())
+}
+
+/// Evaluates a single expression (path followed by filters) without the surrounding braces.
+fn evaluate(expression: &str, input: &Value) -> Result<String, Error> {
+    let mut parts = expression.split('|').map(str::trim);
+    let path = parts.next().unwrap_or_default();
+
+    let mut value = match lookup(input, path)? {
+        Value::String(s) => s.clone(),
+        v @ (Value::Number(_) | Value::Bool(_)) => v.to_string(),
+        Value::Null => return Err(Error::MissingValue(path.to_string())),
+        Value::Array(_) | Value::Object(_) => return Err(Error::NotScalar(path.to_string())),
+    };
+
+    for filter in parts {
+        value = apply_filter(&value, filter)?;
+    }
+
+    Ok(value)
+}
+
+/// Walks `input` along `path`, a leading `.` is optional.
+fn lookup<'a>(input: &'a Value, path: &str) -> Result<&'a Value, Error> {
+    let mut current = input;
+    let mut rest = path.strip_prefix('.').unwrap_or(path);
+
+    if rest.is_empty() {
+        return Err(Error::InvalidPath(path.to_string()));
+    }
+
+    while !rest.is_empty() {
+        let (segment, remaining) =
+            next_segment(rest).ok_or_else(|| Error::InvalidPath(path.to_string()))?;
+
+        current = match segment {
+            Segment::Key(key) => current.get(key),
+            Segment::Index(index) => current.get(index),
+        }
+        .ok_or_else(|| Error::MissingValue(path.to_string()))?;
+
+        rest = remaining;
+    }
+
+    Ok(current)
+}
+
+/// Splits the next segment off of a path, returns `None` if the path is malformed.
+fn next_segment(path: &str) -> Option<(Segment<'_>, &str)> {
+    if let Some(bracketed) = path.strip_prefix('[') {
+        let end = bracketed.find(']')?;
+        let inner = bracketed[..end].trim();
+        let rest = &bracketed[end + 1..];
+
+        let segment = match inner
+            .strip_prefix('"')
+            .and_then(|key| key.strip_suffix('"'))
+        {
+            Some(key) => Segment::Key(key),
+            None => Segment::Index(inner.parse().ok()?),
+        };
+
+        return Some((segment, rest.strip_prefix('.').unwrap_or(rest)));
+    }
+
+    let end = path.find(['.', '[']).unwrap_or(path.len());
+    if end == 0 {
+        return None;
+    }
+
+    let rest = &path[end..];
+
+    Some((
+        Segment::Key(&path[..end]),
+        rest.strip_prefix('.').unwrap_or(rest),
+    ))
+}
+
+/// Applies a named filter to an already rendered value.
+fn apply_filter(value: &str, filter: &str) -> Result<String, Error> {
+    let (registry, repository, tag) = split_image(value);
+
+    match filter {
+        "lower" => Ok(value.to_lowercase()),
+        "upper" => Ok(value.to_uppercase()),
+        "registry" => Ok(registry.to_string()),
+        "repository" => Ok(repository.to_string()),
+        "tag" => Ok(tag.to_string()),
+        _ => Err(Error::UnknownFilter(filter.to_string())),
+    }
+}
+
+/// Splits a container image reference into registry, repository and tag (or digest).
+fn split_image(image: &str) -> (&str, &str, &str) {
+    let (name, digest) = match image.split_once('@') {
+        Some((name, digest)) => (name, Some(digest)),
+        None => (image, None),
+    };
+
+    let (registry, remainder) = match name.split_once('/') {
+        Some((first, rest)) if first.contains(['.', ':']) || first == "localhost" => (first, rest),
+        _ => (DEFAULT_REGISTRY, name),
+    };
+
+    match (remainder.rsplit_once(':'), digest) {
+        (Some((repository, tag)), _) => (registry, repository, tag),
+        (None, Some(digest)) => (registry, remainder, digest),
+        (None, None) => (registry, remainder, "latest"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn pod() -> Value {
+        json!({
+            "metadata": {
+                "name": "web",
+                "namespace": "team-a",
+                "labels": {"app.kubernetes.io/name": "web"}
+            },
+            "spec": {
+                "priority": 10,
+                "containers": [
+                    {"image": "quay.io/org/web:1.2.3"},
+                    {"image": "nginx"}
+                ]
+            }
+        })
+    }
+
+    #[test]
+    fn test_render_paths() {
+        let pod = pod();
+
+        assert_eq!(render("static", &pod), Ok("static".to_string()));
+        assert_eq!(
+            render("{{ metadata.namespace }}", &pod),
+            Ok("team-a".to_string())
+        );
+        assert_eq!(
+            render("{{.metadata.name}}-{{ spec.priority }}", &pod),
+            Ok("web-10".to_string())
+        );
+        assert_eq!(
+            render(r#"{{ metadata.labels["app.kubernetes.io/name"] }}"#, &pod),
+            Ok("web".to_string())
+        );
+        assert_eq!(
+            render("{{ spec.containers[1].image }}", &pod),
+            Ok("nginx".to_string())
+        );
+    }
+
+    #[test]
+    fn test_render_filters() {
+        let pod = pod();
+
+        assert_eq!(
+            render("{{ spec.containers[0].image | registry }}", &pod),
+            Ok("quay.io".to_string())
+        );
+        assert_eq!(
+            render("{{ spec.containers[0].image | repository }}", &pod),
+            Ok("org/web".to_string())
+        );
+        assert_eq!(
+            render("{{ spec.containers[0].image | tag }}", &pod),
+            Ok("1.2.3".to_string())
+        );
+        assert_eq!(
+            render("{{ spec.containers[1].image | registry | upper }}", &pod),
+            Ok("DOCKER.IO".to_string())
+        );
+        assert_eq!(
+            render("{{ spec.containers[1].image | tag }}", &pod),
+            Ok("latest".to_string())
+        );
+    }
+
+    #[test]
+    fn test_render_errors() {
+        let pod = pod();
+
+        assert_eq!(
+            render("{{ metadata.name", &pod),
+            Err(Error::Unclosed("{{ metadata.name".to_string()))
+        );
+        assert_eq!(
+            render("{{ metadata..name }}", &pod),
+            Err(Error::InvalidPath("metadata..name".to_string()))
+        );
+        assert_eq!(
+            render("{{ spec.containers[5].image }}", &pod),
+            Err(Error::MissingValue("spec.containers[5].image".to_string()))
+        );
+        assert_eq!(
+            render("{{ spec.containers }}", &pod),
+            Err(Error::NotScalar("spec.containers".to_string()))
+        );
+        assert_eq!(
+            render("{{ metadata.name | shout }}", &pod),
+            Err(Error::UnknownFilter("shout".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_sanitize_label_value() {
+        assert_eq!(sanitize_label_value(""), Ok(String::new()));
+        assert_eq!(
+            sanitize_label_value("valid.value_1"),
+            Ok("valid.value_1".to_string())
+        );
+        assert_eq!(
+            sanitize_label_value("sha256:abc/def"),
+            Ok("sha256-abc-def".to_string())
+        );
+        assert_eq!(sanitize_label_value("-trimmed-"), Ok("trimmed".to_string()));
+        assert_eq!(sanitize_label_value(&"a".repeat(100)), Ok("a".repeat(63)));
+        assert_eq!(
+            sanitize_label_value("://"),
+            Err(Error::InvalidLabelValue("://".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_split_image() {
+        assert_eq!(split_image("nginx"), ("docker.io", "nginx", "latest"));
+        assert_eq!(
+            split_image("localhost:5000/app:dev"),
+            ("localhost:5000", "app", "dev")
+        );
+        assert_eq!(
+            split_image("ghcr.io/org/app@sha256:abc"),
+            ("ghcr.io", "org/app", "sha256:abc")
+        );
+    }
+}
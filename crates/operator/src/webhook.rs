@@ -0,0 +1,1283 @@
+// Copyright 2026 Stickerbomb Maintainers
+// SPDX-License-Identifier: Apache-2.0
+
+//! Webhooks served to the API server over https.
+//!
+//! The conversion webhook converts `Labeler`s and `ClusterLabeler`s between the served versions, it's
+//! registered in the CRDs by the helm chart. The validating webhook rejects `Labeler`s and `ClusterLabeler`s with invalid
+//! labels, rego or targets at apply time. The mutating webhook labels target resources as they are
+//! created or updated, the reconciliation stays in place for everything it misses. The admission
+//! webhooks are registered by the helm chart as well.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::sync::Arc;
+
+use actix_web::{HttpResponse, post, web};
+use futures::StreamExt;
+use k8s_openapi::api::core::v1::Namespace;
+use kube::api::DynamicObject;
+use kube::core::admission::{AdmissionRequest, AdmissionResponse, AdmissionReview, Operation};
+use kube::core::conversion::{ConversionRequest, ConversionResponse, ConversionReview};
+use kube::core::gvk::GroupVersionKind;
+use kube::core::{PartialObjectMeta, Status};
+use kube::runtime::reflector::{self, Store};
+use kube::runtime::{WatchStreamExt, metadata_watcher, watcher};
+use kube::{Api, Client, Resource, ResourceExt};
+use regorus::Engine;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use stickerbomb_crd::v1_alpha1::{LabelerSpec, RegoRule};
+use stickerbomb_crd::{ClusterLabeler, Labeler, v1_alpha1, v1_beta1};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::controller::{
+    LabelerResource, admission_engine, admission_patch, discover_target_resources, labels_object,
+};
+use crate::policy::{self, PolicySources};
+use crate::{Error, Result};
+
+/// Port of the https server serving the webhooks
+pub const WEBHOOK_PORT: u16 = 8443;
+
+/// Converts every object of a `ConversionReview` to the desired api version, the review fails as a
+/// whole if any of the objects can't be converted.
+#[post("/convert")]
+pub async fn convert(review: web::Json<ConversionReview>) -> HttpResponse {
+    let request = match ConversionRequest::try_from(review.into_inner()) {
+        Ok(request) => request,
+        Err(e) => {
+            warn!(error = %e, "received invalid conversion review");
+            let status = Status::failure(&e.to_string(), "InvalidRequest");
+            return HttpResponse::Ok().json(ConversionResponse::invalid(status).into_review());
+        }
+    };
+
+    let desired = request.desired_api_version.clone();
+    let converted = request
+        .objects
+        .iter()
+        .cloned()
+        .map(|object| convert_object(object, &desired))
+        .collect::<Result<Vec<_>>>();
+
+    let response = ConversionResponse::for_request(request);
+    let response = match converted {
+        Ok(objects) => response.success(objects),
+        Err(e) => {
+            warn!(error = %e, desired_api_version = %desired, "unable to convert objects");
+            response.failure(Status::failure(&e.to_string(), "ConversionFailed"))
+        }
+    };
+
+    HttpResponse::Ok().json(response.into_review())
+}
+
+/// Converts a single `Labeler` or `ClusterLabeler` to the desired api version, objects already in
+/// the desired version are returned as is.
+///
+/// # Errors
+///
+/// This function will return an error if the kind or one of the versions is unknown or the object
+/// can't be parsed.
+pub fn convert_object(object: Value, desired_api_version: &str) -> Result<Value> {
+    let api_version = object["apiVersion"]
+        .as_str()
+        .unwrap_or_default()
+        .to_string();
+    let kind = object["kind"].as_str().unwrap_or_default().to_string();
+
+    if api_version == desired_api_version {
+        return Ok(object);
+    }
+
+    match kind.as_str() {
+        "Labeler" => convert_kind::<v1_alpha1::Labeler, v1_beta1::Labeler>(
+            object,
+            &api_version,
+            desired_api_version,
+        ),
+        "ClusterLabeler" => convert_kind::<v1_alpha1::ClusterLabeler, v1_beta1::ClusterLabeler>(
+            object,
+            &api_version,
+            desired_api_version,
+        ),
+        _ => Err(Error::from(format!(
+            "Unable to convert unknown kind {kind}"
+        ))),
+    }
+}
+
+/// Converts an object between the two versions of a kind.
+///
+/// # Errors
+///
+/// This function will return an error if the versions don't belong to the kind or the object can't
+/// be parsed.
+fn convert_kind<A, B>(object: Value, from: &str, to: &str) -> Result<Value>
+where
+    A: Resource<DynamicType = ()> + DeserializeOwned + Serialize + From<B>,
+    B: Resource<DynamicType = ()> + DeserializeOwned + Serialize + From<A>,
+{
+    let (a, b) = (A::api_version(&()), B::api_version(&()));
+
+    if from == a && to == b {
+        return Ok(serde_json::to_value(B::from(serde_json::from_value::<A>(
+            object,
+        )?))?);
+    }
+
+    if from == b && to == a {
+        return Ok(serde_json::to_value(A::from(serde_json::from_value::<B>(
+            object,
+        )?))?);
+    }
+
+    Err(Error::from(format!(
+        "Unable to convert {} from {from} to {to}",
+        A::kind(&())
+    )))
+}
+
+/// Rejects `Labeler`s and `ClusterLabeler`s that would fail to reconcile, objects being deleted and
+/// updates leaving the spec alone (e.g. removing the finalizer) are always admitted.
+#[post("/validate")]
+pub async fn validate(
+    client: web::Data<Client>,
+    config: web::Data<Config>,
+    review: web::Json<AdmissionReview<DynamicObject>>,
+) -> HttpResponse {
+    let mut request: AdmissionRequest<DynamicObject> = match review.into_inner().try_into() {
+        Ok(request) => request,
+        Err(e) => {
+            warn!(error = %e, "received invalid admission review");
+            return HttpResponse::Ok().json(AdmissionResponse::invalid(e).into_review());
+        }
+    };
+
+    let mut response = AdmissionResponse::from(&request);
+    let Some(mut object) = request
+        .object
+        .take()
+        .filter(|o| o.metadata.deletion_timestamp.is_none())
+    else {
+        return HttpResponse::Ok().json(response.into_review());
+    };
+
+    if request.operation == Operation::Update
+        && request
+            .old_object
+            .as_ref()
+            .is_some_and(|old| old.data.get("spec") == object.data.get("spec"))
+    {
+        return HttpResponse::Ok().json(response.into_review());
+    }
+
+    if object.metadata.namespace.is_none() {
+        object.metadata.namespace.clone_from(&request.namespace);
+    }
+
+    let (errors, warnings) = validate_object(object, &client, &config).await;
+
+    if !warnings.is_empty() {
+        response.warnings = Some(warnings);
+    }
+
+    if !errors.is_empty() {
+        info!(
+            kind = %request.kind.kind,
+            name = %request.name,
+            namespace = request.namespace.as_deref(),
+            errors = errors.len(),
+            "rejected invalid labeler"
+        );
+        response = response.deny(errors.join("; "));
+    }
+
+    HttpResponse::Ok().json(response.into_review())
+}
+
+/// Generation and `status.policyRevision` of the `Labeler` an engine was compiled for
+type EngineKey = (Option<i64>, Option<String>);
+
+/// Compiled engines of the `Labeler`s by uid, with the key they were compiled for
+type EngineCache = Arc<Mutex<HashMap<String, (EngineKey, Engine)>>>;
+
+/// `Labeler`s and `ClusterLabeler`s evaluated by the mutating webhook, the stores are kept up to
+/// date by reflectors on every replica since the webhooks are served regardless of the leadership.
+/// Namespaces are reflected for the namespace selectors and the compiled policies are cached, so
+/// admission requests only reach the API server when a `Labeler` changed.
+#[derive(Clone)]
+pub struct MutationState {
+    client: Client,
+    config: Config,
+    labelers: Store<Labeler>,
+    cluster_labelers: Store<ClusterLabeler>,
+    namespaces: Store<PartialObjectMeta<Namespace>>,
+    engines: EngineCache,
+}
+
+impl MutationState {
+    /// Creates the state and the future running its reflectors, the stores stay empty until the
+    /// future is polled.
+    pub fn new(client: Client, config: Config) -> (Self, impl Future<Output = ()>) {
+        let (labelers, labelers_writer) = reflector::store();
+        let (cluster_labelers, cluster_labelers_writer) = reflector::store();
+        let (namespaces, namespaces_writer) = reflector::store();
+
+        let labelers_stream = reflector::reflector(
+            labelers_writer,
+            watcher(
+                Api::<Labeler>::all(client.clone()),
+                watcher::Config::default().any_semantic(),
+            ),
+        )
+        .default_backoff()
+        .for_each(|_| futures::future::ready(()));
+        let cluster_labelers_stream = reflector::reflector(
+            cluster_labelers_writer,
+            watcher(
+                Api::<ClusterLabeler>::all(client.clone()),
+                watcher::Config::default().any_semantic(),
+            ),
+        )
+        .default_backoff()
+        .for_each(|_| futures::future::ready(()));
+        let namespaces_stream = reflector::reflector(
+            namespaces_writer,
+            metadata_watcher(
+                Api::<Namespace>::all(client.clone()),
+                watcher::Config::default().any_semantic(),
+            ),
+        )
+        .default_backoff()
+        .for_each(|_| futures::future::ready(()));
+
+        let state = Self {
+            client,
+            config,
+            labelers,
+            cluster_labelers,
+            namespaces,
+            engines: EngineCache::default(),
+        };
+        let reflectors = async move {
+            futures::join!(labelers_stream, cluster_labelers_stream, namespaces_stream);
+        };
+
+        (state, reflectors)
+    }
+
+    /// Applies the merge patch of every matching `ClusterLabeler` and `Labeler` to the object in
+    /// turn, returns the json patch between the original and the labeled object or `None` if
+    /// nothing changed.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the object can't be serialized.
+    async fn label(
+        &self,
+        object: &DynamicObject,
+        kind: &GroupVersionKind,
+    ) -> Result<Option<json_patch::Patch>> {
+        let original = serde_json::to_value(object)?;
+        let mut labeled = original.clone();
+
+        self.apply(&self.cluster_labelers, kind, &mut labeled)
+            .await?;
+        self.apply(&self.labelers, kind, &mut labeled).await?;
+
+        let patch = json_patch::diff(&original, &labeled);
+        Ok((!patch.0.is_empty()).then_some(patch))
+    }
+
+    /// Merges the patches of the `Labeler`s in the store into the object ordered by namespace and
+    /// name, `Labeler`s that fail to evaluate are skipped and left to the reconciliation.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the labeled object can't be parsed.
+    async fn apply<K: LabelerResource>(
+        &self,
+        store: &Store<K>,
+        kind: &GroupVersionKind,
+        labeled: &mut Value,
+    ) -> Result<()> {
+        let mut labelers = store.state();
+        labelers.sort_by_key(|labeler| (labeler.namespace(), labeler.name_any()));
+
+        for labeler in labelers {
+            let object: DynamicObject = serde_json::from_value(labeled.clone())?;
+
+            let patch = match labels_object(
+                labeler.as_ref(),
+                kind,
+                &object.metadata,
+                &self.namespaces,
+                &self.config,
+            ) {
+                Ok(true) => match self.engine(labeler.as_ref()).await {
+                    Ok(mut engine) => admission_patch(labeler.as_ref(), &object, &mut engine),
+                    Err(e) => Err(e),
+                },
+                Ok(false) => Ok(None),
+                Err(e) => Err(e),
+            };
+
+            match patch {
+                Ok(Some(patch)) => json_patch::merge(labeled, &patch),
+                Ok(None) => {}
+                Err(e) => warn!(
+                    error = %e,
+                    labeler_kind = %K::kind(&()),
+                    labeler_name = %labeler.name_any(),
+                    labeler_namespace = labeler.namespace().as_deref(),
+                    "unable to evaluate labeler for admission"
+                ),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns a copy of the `Labeler`'s compiled engine, the engine is compiled again when the
+    /// `Labeler`'s generation changed. Changed `ConfigMap`s or `RegoLibrary`s are picked up once
+    /// the reconciliation records the new `status.policyRevision`, other status updates keep the
+    /// cached engine.
+    ///
+    /// # Errors
+    ///
+    /// This function will return an error if the policy can't be loaded or compiled.
+    async fn engine<K: LabelerResource>(&self, labeler: &K) -> Result<Engine> {
+        let uid = labeler.uid().unwrap_or_default();
+        let key = (
+            labeler.meta().generation,
+            labeler
+                .status()
+                .and_then(|status| status.policy_revision.clone()),
+        );
+
+        if let Some((cached, engine)) = self.engines.lock().await.get(&uid)
+            && *cached == key
+        {
+            return Ok(engine.clone());
+        }
+
+        let engine = admission_engine(labeler, &self.client, &self.config).await?;
+
+        let mut engines = self.engines.lock().await;
+        let (labelers, cluster_labelers) = (self.labelers.state(), self.cluster_labelers.state());
+        // Drops the engines of deleted `Labeler`s
+        engines.retain(|uid, _| {
+            labelers
+                .iter()
+                .map(|labeler| labeler.uid())
+                .chain(cluster_labelers.iter().map(|labeler| labeler.uid()))
+                .any(|known| known.as_ref() == Some(uid))
+        });
+        engines.insert(uid, (key, engine.clone()));
+
+        Ok(engine)
+    }
+}
+
+/// Labels target resources on creation and update with every matching `Labeler`, the object is
+/// always admitted and left to the reconciliation if it can't be labeled.
+#[post("/mutate")]
+pub async fn mutate(
+    state: web::Data<MutationState>,
+    review: web::Json<AdmissionReview<DynamicObject>>,
+) -> HttpResponse {
+    let mut request: AdmissionRequest<DynamicObject> = match review.into_inner().try_into() {
+        Ok(request) => request,
+        Err(e) => {
+            warn!(error = %e, "received invalid admission review");
+            return HttpResponse::Ok().json(AdmissionResponse::invalid(e).into_review());
+        }
+    };
+
+    let response = AdmissionResponse::from(&request);
+    let Some(mut object) = request
+        .object
+        .take()
+        .filter(|o| o.metadata.deletion_timestamp.is_none())
+    else {
+        return HttpResponse::Ok().json(response.into_review());
+    };
+
+    // Objects created in a namespace don't necessarily carry it in their metadata yet
+    if object.metadata.namespace.is_none() {
+        object.metadata.namespace.clone_from(&request.namespace);
+    }
+
+    let patched = state
+        .label(&object, &request.kind)
+        .await
+        .and_then(|patch| match patch {
+            Some(patch) => response
+                .clone()
+                .with_patch(patch)
+                .map_err(|e| Error::from(e.to_string())),
+            None => Ok(response.clone()),
+        });
+
+    let response = match patched {
+        Ok(response) => response,
+        Err(e) => {
+            warn!(
+                error = %e,
+                kind = %request.kind.kind,
+                name = %request.name,
+                namespace = request.namespace.as_deref(),
+                "unable to label object on admission"
+            );
+            response
+        }
+    };
+
+    HttpResponse::Ok().json(response.into_review())
+}
+
+/// Validates a `Labeler` or `ClusterLabeler` of any served version, returns the errors and the
+/// warnings found. Targets that can't be checked because the discovery api failed and policies
+/// whose referenced modules can't be loaded only produce a warning.
+pub async fn validate_object(
+    object: DynamicObject,
+    client: &Client,
+    config: &Config,
+) -> (Vec<String>, Vec<String>) {
+    let converted = serde_json::to_value(object)
+        .map_err(Error::from)
+        .and_then(|object| convert_object(object, &v1_alpha1::Labeler::api_version(&())))
+        .and_then(|object| {
+            let spec = serde_json::from_value::<LabelerSpec>(object["spec"].clone())?;
+            Ok((object, spec))
+        });
+
+    let (object, spec) = match converted {
+        Ok(converted) => converted,
+        Err(e) => return (vec![format!("Unable to parse spec: {e}")], Vec::new()),
+    };
+
+    let mut errors = validate_spec(&spec);
+    let mut warnings = Vec::new();
+
+    if let Some(rule) = &spec.rego {
+        match load_sources(object, client, config).await {
+            Ok(sources) => {
+                if let Err(e) = check_rego(rule, &sources) {
+                    errors.push(format!("Invalid rego: {e}"));
+                }
+            }
+            Err(e) => warnings.push(format!(
+                "Unable to load the policy, it is checked on reconciliation: {e}"
+            )),
+        }
+    }
+
+    for target in spec.targets() {
+        let (api, kind) = (&target.resource_api, &target.resource_kind);
+
+        match discover_target_resources(&target, client).await {
+            Ok(_) => {}
+            Err(Error::KubeError(kube::Error::Api(response))) if response.code == 404 => {
+                errors.push(format!("resourceApi {api} is not served by the cluster"));
+            }
+            Err(e @ (Error::KindNotServed { .. } | Error::ParseError(_))) => {
+                errors.push(e.to_string());
+            }
+            Err(e) => warnings.push(format!("Unable to discover {kind} in {api}: {e}")),
+        }
+    }
+
+    (errors, warnings)
+}
+
+/// Checks the labels, the annotations and the targets of a spec without calling the API server.
+fn validate_spec(spec: &LabelerSpec) -> Vec<String> {
+    let mut errors = spec.validate().err().map(|e| e.0).unwrap_or_default();
+
+    if spec.targets().is_empty() {
+        errors.push(
+            "resourceApi and resourceKind or at least one entry in targets must be set".to_string(),
+        );
+    }
+
+    errors
+}
+
+/// Loads the policy sources of a `v1alpha1` `Labeler` or `ClusterLabeler` object the same way the
+/// reconciliation does.
+///
+/// # Errors
+///
+/// This function will return an error if the object can't be parsed or the sources can't be
+/// loaded.
+async fn load_sources(object: Value, client: &Client, config: &Config) -> Result<PolicySources> {
+    if object["kind"] == ClusterLabeler::kind(&()).as_ref() {
+        let labeler: ClusterLabeler = serde_json::from_value(object)?;
+        policy::load_sources(&labeler, client, config).await
+    } else {
+        let labeler: Labeler = serde_json::from_value(object)?;
+        policy::load_sources(&labeler, client, config).await
+    }
+}
+
+/// Compiles the inline policy with the modules and data loaded from `policyRef`, `dataRef` and the
+/// imported `RegoLibrary`s and evaluates the query against an empty input.
+///
+/// # Errors
+///
+/// This function will return an error if the policy can't be compiled or the query can't be
+/// evaluated.
+fn check_rego(rule: &RegoRule, sources: &PolicySources) -> Result<()> {
+    let mut engine = Engine::new();
+
+    if !rule.policy.is_empty() {
+        engine
+            .add_policy("policy.rego".to_string(), rule.policy.clone())
+            .map_err(policy::rego_error)?;
+    }
+
+    sources.add_to(&mut engine)?;
+
+    engine.set_input_json("{}").map_err(policy::rego_error)?;
+    engine
+        .eval_query(rule.query.clone(), false)
+        .map_err(policy::rego_error)?;
+
+    Ok(())
+}
+
+/// Builds the tls configuration of the webhook server from the `tls.crt` and `tls.key` files in the
+/// certificate directory.
+///
+/// # Errors
+///
+/// This function will return an error if the certificate or the key can't be read or don't match.
+pub fn tls_config(cert_dir: &Path) -> Result<rustls::ServerConfig> {
+    let certs = CertificateDer::pem_file_iter(cert_dir.join("tls.crt"))
+        .and_then(Iterator::collect::<std::result::Result<Vec<_>, _>>)
+        .map_err(|e| Error::from(format!("Unable to read webhook certificate: {e}")))?;
+    let key = PrivateKeyDer::from_pem_file(cert_dir.join("tls.key"))
+        .map_err(|e| Error::from(format!("Unable to read webhook private key: {e}")))?;
+
+    let config = rustls::ServerConfig::builder_with_provider(Arc::new(
+        rustls::crypto::ring::default_provider(),
+    ))
+    .with_safe_default_protocol_versions()?
+    .with_no_client_auth()
+    .with_single_cert(certs, key)?;
+
+    Ok(config)
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::App;
+    use actix_web::test::{TestRequest, call_and_read_body_json, init_service};
+    use serde_json::json;
+
+    use super::*;
+
+    fn alpha_labeler() -> Value {
+        json!({
+            "apiVersion": "stickerbomb.dev/v1alpha1",
+            "kind": "Labeler",
+            "metadata": {"name": "web", "namespace": "team-a", "uid": "1234"},
+            "spec": {
+                "resourceApi": "apps/v1",
+                "resourceKind": "Deployment",
+                "targets": [{"resourceApi": "apps/v1", "resourceKind": "StatefulSet"}],
+                "labels": {"team": "a"},
+                "conflictPolicy": "Preserve",
+                "pruneOnMismatch": true
+            },
+            "status": {"resourcesMatched": 2, "resourcesLabeled": 1, "resourcesSkipped": 1}
+        })
+    }
+
+    #[test]
+    fn test_convert_object_round_trip() {
+        let beta = convert_object(alpha_labeler(), "stickerbomb.dev/v1beta1").unwrap();
+
+        assert_eq!(beta["apiVersion"], "stickerbomb.dev/v1beta1");
+        assert_eq!(
+            beta["spec"]["targets"],
+            json!([
+                {"resourceApi": "apps/v1", "resourceKind": "Deployment"},
+                {"resourceApi": "apps/v1", "resourceKind": "StatefulSet"}
+            ])
+        );
+        assert_eq!(beta["spec"].get("resourceApi"), None);
+        assert_eq!(beta["metadata"]["uid"], "1234");
+        assert_eq!(beta["status"]["resourcesMatched"], 2);
+
+        let alpha = convert_object(beta, "stickerbomb.dev/v1alpha1").unwrap();
+        assert_eq!(
+            serde_json::from_value::<v1_alpha1::Labeler>(alpha)
+                .unwrap()
+                .spec
+                .targets(),
+            serde_json::from_value::<v1_alpha1::Labeler>(alpha_labeler())
+                .unwrap()
+                .spec
+                .targets()
+        );
+    }
+
+    #[test]
+    fn test_convert_object_keeps_duplicate_targets() {
+        let mut object = alpha_labeler();
+        object["spec"]["targets"] = json!([
+            {"resourceApi": "apps/v1", "resourceKind": "Deployment"},
+            {"resourceApi": "apps/v1", "resourceKind": "StatefulSet"}
+        ]);
+        let original = serde_json::to_value(
+            serde_json::from_value::<v1_alpha1::Labeler>(object.clone()).unwrap(),
+        )
+        .unwrap();
+
+        let beta = convert_object(object, "stickerbomb.dev/v1beta1").unwrap();
+        assert_eq!(beta["spec"]["targets"].as_array().map(Vec::len), Some(3));
+
+        let alpha = convert_object(beta, "stickerbomb.dev/v1alpha1").unwrap();
+        assert_eq!(alpha, original);
+    }
+
+    #[test]
+    fn test_convert_object_keeps_alpha_targets() {
+        let mut object = alpha_labeler();
+        object["spec"]
+            .as_object_mut()
+            .unwrap()
+            .remove("resourceApi");
+        object["spec"]
+            .as_object_mut()
+            .unwrap()
+            .remove("resourceKind");
+        object["spec"]["targets"] = json!([
+            {"resourceApi": "apps/v1", "resourceKind": "Deployment"},
+            {"resourceApi": "apps/v1", "resourceKind": "StatefulSet"}
+        ]);
+        let original = serde_json::to_value(
+            serde_json::from_value::<v1_alpha1::Labeler>(object.clone()).unwrap(),
+        )
+        .unwrap();
+
+        let beta = convert_object(object, "stickerbomb.dev/v1beta1").unwrap();
+        assert_eq!(beta["spec"]["targets"].as_array().map(Vec::len), Some(2));
+        assert!(
+            beta["metadata"]["annotations"]
+                .get(v1_beta1::ALPHA_TARGETS_ANNOTATION)
+                .is_some()
+        );
+
+        let alpha = convert_object(beta, "stickerbomb.dev/v1alpha1").unwrap();
+        assert_eq!(alpha, original);
+    }
+
+    #[test]
+    fn test_convert_object_keeps_incomplete_target() {
+        let mut object = alpha_labeler();
+        object["spec"]
+            .as_object_mut()
+            .unwrap()
+            .remove("resourceKind");
+        let original = serde_json::to_value(
+            serde_json::from_value::<v1_alpha1::Labeler>(object.clone()).unwrap(),
+        )
+        .unwrap();
+
+        let beta = convert_object(object, "stickerbomb.dev/v1beta1").unwrap();
+        assert_eq!(
+            beta["spec"]["targets"],
+            json!([{"resourceApi": "apps/v1", "resourceKind": "StatefulSet"}])
+        );
+
+        let alpha = convert_object(beta, "stickerbomb.dev/v1alpha1").unwrap();
+        assert_eq!(alpha, original);
+        assert_eq!(alpha["spec"]["resourceApi"], "apps/v1");
+    }
+
+    #[test]
+    fn test_convert_object_ignores_stale_alpha_targets() {
+        let mut object = alpha_labeler();
+        object["spec"]
+            .as_object_mut()
+            .unwrap()
+            .remove("resourceKind");
+        object["metadata"]["annotations"] = json!({"team": "a"});
+
+        let mut beta = convert_object(object, "stickerbomb.dev/v1beta1").unwrap();
+        beta["spec"]["targets"] = json!([{"resourceApi": "v1", "resourceKind": "Pod"}]);
+
+        let alpha = convert_object(beta, "stickerbomb.dev/v1alpha1").unwrap();
+        assert_eq!(alpha["spec"]["resourceApi"], "v1");
+        assert_eq!(alpha["spec"]["resourceKind"], "Pod");
+        assert_eq!(alpha["spec"].get("targets"), None);
+        assert_eq!(alpha["metadata"]["annotations"], json!({"team": "a"}));
+    }
+
+    #[test]
+    fn test_convert_object_cluster_labeler() {
+        let mut object = alpha_labeler();
+        object["kind"] = json!("ClusterLabeler");
+        object["metadata"] = json!({"name": "web"});
+
+        let beta = convert_object(object, "stickerbomb.dev/v1beta1").unwrap();
+        assert_eq!(beta["kind"], "ClusterLabeler");
+        assert_eq!(beta["spec"]["targets"].as_array().map(Vec::len), Some(2));
+    }
+
+    #[test]
+    fn test_convert_object_errors() {
+        let same = convert_object(alpha_labeler(), "stickerbomb.dev/v1alpha1").unwrap();
+        assert_eq!(same, alpha_labeler());
+
+        assert!(convert_object(alpha_labeler(), "stickerbomb.dev/v2").is_err());
+
+        let mut unknown = alpha_labeler();
+        unknown["kind"] = json!("Stamp");
+        assert!(convert_object(unknown, "stickerbomb.dev/v1beta1").is_err());
+    }
+
+    #[test]
+    fn test_validate_spec() {
+        let spec = serde_json::from_value::<LabelerSpec>(json!({
+            "resourceApi": "v1",
+            "resourceKind": "Pod",
+            "labels": {"team": "a", "bad key": "b"}
+        }))
+        .unwrap();
+
+        let errors = validate_spec(&spec);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("bad key"));
+
+        let spec = serde_json::from_value::<LabelerSpec>(json!({"labels": {"team": "a"}})).unwrap();
+        assert_eq!(validate_spec(&spec).len(), 1);
+    }
+
+    #[test]
+    fn test_check_rego() {
+        let rule = |policy: &str, query: &str| {
+            serde_json::from_value::<RegoRule>(json!({"policy": policy, "query": query})).unwrap()
+        };
+
+        let sources = PolicySources {
+            data: vec![json!({"registries": ["ghcr.io"]})],
+            ..PolicySources::default()
+        };
+        let allow = rule(
+            "package test\nallow if data.registries[_] == \"ghcr.io\"",
+            "data.test.allow",
+        );
+        assert!(check_rego(&allow, &sources).is_ok());
+        assert!(
+            check_rego(
+                &rule("package test\nallow if {", "data.test.allow"),
+                &sources
+            )
+            .is_err()
+        );
+        assert!(check_rego(&allow, &PolicySources::default()).is_ok());
+        assert!(check_rego(&rule("", "data.test.allow ==="), &sources).is_err());
+
+        // Rules of imported libraries are only defined with the loaded modules
+        let imports = rule(
+            "package test\nimport data.lib.images as images\nallow if images.trusted",
+            "data.test.allow",
+        );
+        let library = PolicySources {
+            modules: vec![(
+                "images/images.rego".to_string(),
+                "package lib.images\ntrusted := true".to_string(),
+            )],
+            ..PolicySources::default()
+        };
+        assert!(check_rego(&imports, &library).is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_validate_object_loads_policy_ref() {
+        use http::{Request, Response};
+        use kube::client::Body;
+        use tower_test::mock;
+
+        let (mock_service, mut handle) = mock::pair::<Request<Body>, Response<Body>>();
+        let client = Client::new(mock_service, "default");
+
+        tokio::spawn(async move {
+            let (request, send) = handle.next_request().await.unwrap();
+            assert_eq!(
+                request.uri().path(),
+                "/api/v1/namespaces/team-a/configmaps/policies"
+            );
+
+            let config_map = json!({
+                "apiVersion": "v1",
+                "kind": "ConfigMap",
+                "metadata": {"name": "policies", "namespace": "team-a", "resourceVersion": "1"},
+                "data": {"policy.rego": "package test\nallow if input.kind == \"Pod\""}
+            });
+            send.send_response(
+                Response::builder()
+                    .body(Body::from(serde_json::to_vec(&config_map).unwrap()))
+                    .unwrap(),
+            );
+
+            let (request, send) = handle.next_request().await.unwrap();
+            assert_eq!(request.uri().path(), "/api/v1");
+
+            let resources = json!({
+                "kind": "APIResourceList",
+                "apiVersion": "v1",
+                "groupVersion": "v1",
+                "resources": [{
+                    "name": "pods",
+                    "singularName": "pod",
+                    "namespaced": true,
+                    "kind": "Pod",
+                    "verbs": ["get", "list", "watch", "patch"]
+                }]
+            });
+            send.send_response(
+                Response::builder()
+                    .body(Body::from(serde_json::to_vec(&resources).unwrap()))
+                    .unwrap(),
+            );
+        });
+
+        let mut object = alpha_labeler();
+        object["spec"]["targets"] = json!([]);
+        object["spec"]["resourceApi"] = json!("v1");
+        object["spec"]["resourceKind"] = json!("Pod");
+        object["spec"]["rego"] = json!({
+            "policyRef": {"name": "policies"},
+            "query": "data.test.allow"
+        });
+
+        let (errors, warnings) = validate_object(
+            serde_json::from_value(object).unwrap(),
+            &client,
+            &Config::default(),
+        )
+        .await;
+        assert!(errors.is_empty(), "{errors:?}");
+        assert!(warnings.is_empty(), "{warnings:?}");
+    }
+
+    #[actix_web::test]
+    async fn test_validate_endpoint() {
+        use http::{Request, Response};
+        use kube::client::Body;
+        use tower_test::mock;
+
+        let (mock_service, mut handle) = mock::pair::<Request<Body>, Response<Body>>();
+        let client = Client::new(mock_service, "default");
+
+        tokio::spawn(async move {
+            let (request, send) = handle.next_request().await.unwrap();
+            assert_eq!(request.uri().path(), "/apis/example.com/v1");
+
+            let status = json!({
+                "kind": "Status",
+                "apiVersion": "v1",
+                "status": "Failure",
+                "message": "the server could not find the requested resource",
+                "reason": "NotFound",
+                "code": 404
+            });
+            send.send_response(
+                Response::builder()
+                    .status(404)
+                    .body(Body::from(serde_json::to_vec(&status).unwrap()))
+                    .unwrap(),
+            );
+        });
+
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(client))
+                .app_data(web::Data::new(Config::default()))
+                .service(validate),
+        )
+        .await;
+
+        let mut object = alpha_labeler();
+        object["spec"]["targets"] = json!([]);
+        object["spec"]["resourceApi"] = json!("example.com/v1");
+        let req = TestRequest::post()
+            .uri("/validate")
+            .set_json(json!({
+                "apiVersion": "admission.k8s.io/v1",
+                "kind": "AdmissionReview",
+                "request": {
+                    "uid": "review-2",
+                    "kind": {"group": "stickerbomb.dev", "version": "v1alpha1", "kind": "Labeler"},
+                    "resource": {"group": "stickerbomb.dev", "version": "v1alpha1", "resource": "labelers"},
+                    "name": "web",
+                    "namespace": "team-a",
+                    "operation": "CREATE",
+                    "userInfo": {"username": "admin"},
+                    "object": object,
+                    "dryRun": false
+                }
+            }))
+            .to_request();
+
+        let body: Value = call_and_read_body_json(&app, req).await;
+
+        assert_eq!(body["response"]["uid"], "review-2");
+        assert_eq!(body["response"]["allowed"], false);
+        assert_eq!(
+            body["response"]["status"]["message"],
+            "resourceApi example.com/v1 is not served by the cluster"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_validate_endpoint_skips_unchanged_spec() {
+        use http::{Request, Response};
+        use kube::client::Body;
+        use tower_test::mock;
+
+        // The API server is never called, the handle is dropped right away
+        let (mock_service, _) = mock::pair::<Request<Body>, Response<Body>>();
+        let client = Client::new(mock_service, "default");
+
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(client))
+                .app_data(web::Data::new(Config::default()))
+                .service(validate),
+        )
+        .await;
+
+        // Labelers stored before the validation existed may be invalid, removing their finalizer
+        // must still be admitted
+        let mut old_object = alpha_labeler();
+        old_object["spec"]["labels"] = json!({"bad key": "a"});
+        old_object["metadata"]["finalizers"] = json!(["labelers.stickerbomb.dev/cleanup"]);
+        let mut object = old_object.clone();
+        object["metadata"]["finalizers"] = json!([]);
+
+        let review = |object: &Value| {
+            json!({
+                "apiVersion": "admission.k8s.io/v1",
+                "kind": "AdmissionReview",
+                "request": {
+                    "uid": "review-3",
+                    "kind": {"group": "stickerbomb.dev", "version": "v1alpha1", "kind": "Labeler"},
+                    "resource": {"group": "stickerbomb.dev", "version": "v1alpha1", "resource": "labelers"},
+                    "name": "web",
+                    "namespace": "team-a",
+                    "operation": "UPDATE",
+                    "userInfo": {"username": "admin"},
+                    "object": object,
+                    "oldObject": old_object,
+                    "dryRun": false
+                }
+            })
+        };
+
+        let req = TestRequest::post()
+            .uri("/validate")
+            .set_json(review(&object))
+            .to_request();
+        let body: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(body["response"]["allowed"], true);
+
+        object["spec"]["labels"] = json!({"bad key": "b"});
+        let req = TestRequest::post()
+            .uri("/validate")
+            .set_json(review(&object))
+            .to_request();
+        let body: Value = call_and_read_body_json(&app, req).await;
+        assert_eq!(body["response"]["allowed"], false);
+    }
+
+    #[tokio::test]
+    async fn test_validate_object_kind_not_served() {
+        use http::{Request, Response};
+        use kube::client::Body;
+        use tower_test::mock;
+
+        let (mock_service, mut handle) = mock::pair::<Request<Body>, Response<Body>>();
+        let client = Client::new(mock_service, "default");
+
+        tokio::spawn(async move {
+            let (request, send) = handle.next_request().await.unwrap();
+            assert_eq!(request.uri().path(), "/apis/example.com/v1");
+
+            let resources = json!({
+                "kind": "APIResourceList",
+                "apiVersion": "v1",
+                "groupVersion": "example.com/v1",
+                "resources": [{
+                    "name": "gadgets",
+                    "singularName": "gadget",
+                    "namespaced": true,
+                    "kind": "Gadget",
+                    "verbs": ["get", "list", "watch", "patch"]
+                }]
+            });
+            send.send_response(
+                Response::builder()
+                    .body(Body::from(serde_json::to_vec(&resources).unwrap()))
+                    .unwrap(),
+            );
+        });
+
+        let mut object = alpha_labeler();
+        object["spec"]["targets"] = json!([]);
+        object["spec"]["resourceApi"] = json!("example.com/v1");
+        object["spec"]["resourceKind"] = json!("Widget");
+
+        let (errors, warnings) = validate_object(
+            serde_json::from_value(object).unwrap(),
+            &client,
+            &Config::default(),
+        )
+        .await;
+        assert_eq!(
+            errors,
+            vec!["resourceKind Widget is not served by example.com/v1".to_string()]
+        );
+        assert!(warnings.is_empty());
+    }
+
+    fn mutation_state() -> MutationState {
+        use http::{Request, Response};
+        use kube::client::Body;
+        use kube::runtime::watcher::Event;
+        use tower_test::mock;
+
+        let (mock_service, _handle) = mock::pair::<Request<Body>, Response<Body>>();
+        let (labelers, mut labelers_writer) = reflector::store();
+        let (cluster_labelers, mut cluster_labelers_writer) = reflector::store();
+
+        let labeler: Labeler = serde_json::from_value(json!({
+            "apiVersion": "stickerbomb.dev/v1alpha1",
+            "kind": "Labeler",
+            "metadata": {"name": "team", "namespace": "team-a", "uid": "1234"},
+            "spec": {"resourceApi": "v1", "resourceKind": "Pod", "labels": {"team": "a"}}
+        }))
+        .unwrap();
+        labelers_writer.apply_watcher_event(&Event::Apply(labeler));
+
+        let cluster_labeler: ClusterLabeler = serde_json::from_value(json!({
+            "apiVersion": "stickerbomb.dev/v1alpha1",
+            "kind": "ClusterLabeler",
+            "metadata": {"name": "tier", "uid": "5678"},
+            "spec": {
+                "resourceApi": "v1",
+                "resourceKind": "Pod",
+                "labels": {"example.com/tier": "web"},
+                "rego": {"policy": "package tier\nweb if input.metadata.labels.app == \"web\"", "query": "data.tier.web"}
+            }
+        }))
+        .unwrap();
+        cluster_labelers_writer.apply_watcher_event(&Event::Apply(cluster_labeler));
+
+        MutationState {
+            client: Client::new(mock_service, "default"),
+            config: Config::default(),
+            labelers,
+            cluster_labelers,
+            namespaces: reflector::store().0,
+            engines: EngineCache::default(),
+        }
+    }
+
+    fn pod(namespace: &str, app: &str) -> Value {
+        json!({
+            "apiVersion": "v1",
+            "kind": "Pod",
+            "metadata": {"name": "web", "namespace": namespace, "labels": {"app": app}},
+            "spec": {"containers": [{"name": "web", "image": "nginx"}]}
+        })
+    }
+
+    #[tokio::test]
+    async fn test_mutation_label() {
+        let state = mutation_state();
+        let kind = GroupVersionKind::gvk("", "v1", "Pod");
+
+        let mut object = pod("team-a", "web");
+        let patch = state
+            .label(&serde_json::from_value(object.clone()).unwrap(), &kind)
+            .await
+            .unwrap()
+            .unwrap();
+        json_patch::patch(&mut object, &patch).unwrap();
+        assert_eq!(
+            object["metadata"]["labels"],
+            json!({"app": "web", "example.com/tier": "web", "team": "a"})
+        );
+
+        let mut object = pod("team-b", "db");
+        assert!(
+            state
+                .label(&serde_json::from_value(object.clone()).unwrap(), &kind)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        object["metadata"]["labels"] = json!({"app": "web", "example.com/tier": "web"});
+        assert!(
+            state
+                .label(&serde_json::from_value(object).unwrap(), &kind)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_mutation_engine_cache() {
+        let state = mutation_state();
+        let mut labeler = state.labelers.state()[0].as_ref().clone();
+        labeler.metadata.generation = Some(1);
+        labeler.metadata.resource_version = Some("1".to_string());
+
+        // Marks the cached engine to tell it apart from a freshly compiled one
+        let cache = |key: EngineKey| async {
+            let mut cached = Engine::new();
+            cached
+                .add_policy("cached.rego".to_string(), "package cached".to_string())
+                .unwrap();
+            state
+                .engines
+                .lock()
+                .await
+                .insert("1234".to_string(), (key, cached));
+        };
+        let is_cached = |engine: &Engine| {
+            engine
+                .get_policies()
+                .unwrap()
+                .iter()
+                .any(|policy| policy.get_path() == "cached.rego")
+        };
+
+        // Status updates only bump the resource version
+        cache((Some(1), None)).await;
+        labeler.metadata.resource_version = Some("2".to_string());
+        assert!(is_cached(&state.engine(&labeler).await.unwrap()));
+
+        // A new policy revision or generation compiles the engine again
+        labeler.status = Some(stickerbomb_crd::LabelerStatus {
+            policy_revision: Some("team-a/policy@2".to_string()),
+            ..Default::default()
+        });
+        assert!(!is_cached(&state.engine(&labeler).await.unwrap()));
+
+        cache((Some(1), Some("team-a/policy@2".to_string()))).await;
+        labeler.metadata.generation = Some(2);
+        assert!(!is_cached(&state.engine(&labeler).await.unwrap()));
+    }
+
+    #[tokio::test]
+    async fn test_mutation_skips_deleting_labelers() {
+        use k8s_openapi::apimachinery::pkg::apis::meta::v1::Time;
+        use k8s_openapi::chrono::Utc;
+        use kube::runtime::watcher::Event;
+
+        let mut state = mutation_state();
+        let kind = GroupVersionKind::gvk("", "v1", "Pod");
+
+        // Only the `Labeler` being deleted targets pods in team-a, its cleanup passes through here
+        let (labelers, mut writer) = reflector::store();
+        for labeler in state.labelers.state() {
+            let mut labeler = labeler.as_ref().clone();
+            labeler.metadata.deletion_timestamp = Some(Time(Utc::now()));
+            writer.apply_watcher_event(&Event::Apply(labeler));
+        }
+        state.labelers = labelers;
+        state.cluster_labelers = reflector::store().0;
+
+        let object = pod("team-a", "web");
+        assert!(
+            state
+                .label(&serde_json::from_value(object).unwrap(), &kind)
+                .await
+                .unwrap()
+                .is_none()
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_mutate_endpoint() {
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(mutation_state()))
+                .service(mutate),
+        )
+        .await;
+
+        let mut object = pod("team-a", "db");
+        object["metadata"]
+            .as_object_mut()
+            .unwrap()
+            .remove("namespace");
+        let req = TestRequest::post()
+            .uri("/mutate")
+            .set_json(json!({
+                "apiVersion": "admission.k8s.io/v1",
+                "kind": "AdmissionReview",
+                "request": {
+                    "uid": "review-3",
+                    "kind": {"group": "", "version": "v1", "kind": "Pod"},
+                    "resource": {"group": "", "version": "v1", "resource": "pods"},
+                    "name": "web",
+                    "namespace": "team-a",
+                    "operation": "CREATE",
+                    "userInfo": {"username": "admin"},
+                    "object": object,
+                    "dryRun": false
+                }
+            }))
+            .to_request();
+
+        let body: Value = call_and_read_body_json(&app, req).await;
+
+        assert_eq!(body["response"]["uid"], "review-3");
+        assert_eq!(body["response"]["allowed"], true);
+        assert_eq!(body["response"]["patchType"], "JSONPatch");
+    }
+
+    #[actix_web::test]
+    async fn test_convert_endpoint() {
+        let app = init_service(App::new().service(convert)).await;
+        let req = TestRequest::post()
+            .uri("/convert")
+            .set_json(json!({
+                "apiVersion": "apiextensions.k8s.io/v1",
+                "kind": "ConversionReview",
+                "request": {
+                    "uid": "review-1",
+                    "desiredAPIVersion": "stickerbomb.dev/v1beta1",
+                    "objects": [alpha_labeler()]
+                }
+            }))
+            .to_request();
+
+        let body: Value = call_and_read_body_json(&app, req).await;
+
+        assert_eq!(body["response"]["uid"], "review-1");
+        assert_eq!(body["response"]["result"]["status"], "Success");
+        assert_eq!(
+            body["response"]["convertedObjects"][0]["apiVersion"],
+            "stickerbomb.dev/v1beta1"
+        );
+    }
+}